@@ -27,6 +27,9 @@ impl Audio {
             return;
         };
 
+        // Replace any pending notification, e.g. when a paused timer is resumed.
+        self.cancel_notify();
+
         self.run_flag.store(true, Ordering::SeqCst);
         let run_flag = Arc::clone(&self.run_flag);
 
@@ -0,0 +1,181 @@
+use crate::{
+    error::Error,
+    history::{self, History, HistoryArchive},
+    setting::Setting,
+};
+use chrono::{DateTime, Local};
+use eframe::egui::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+const PREFIX: &str = "work_timer_";
+pub const EXTENSION: &str = "json";
+
+/// A single file holding the history and the setting, it can be restored on
+/// another computer.
+#[derive(Deserialize, Serialize)]
+struct Archive {
+    /// `Archive::VERSION` when written
+    version: u8,
+    /// Seconds since epoch
+    created: u64,
+    /// The content of the config file
+    setting: String,
+    history: HistoryArchive,
+}
+
+impl Archive {
+    const VERSION: u8 = 1;
+}
+
+/// A backup file which is checked to be restorable.
+pub struct Backup {
+    archive: Archive,
+}
+
+impl Backup {
+    pub fn read(file_path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(file_path)?;
+        let archive: Archive = serde_json::from_str(&text)?;
+        if archive.version > Archive::VERSION {
+            return Err(Error::Invalid(format!(
+                "Unsupported backup version {}",
+                archive.version
+            )));
+        }
+        if history::time_from_secs(archive.created).is_none() {
+            return Err(Error::Invalid("Invalid backup time".to_string()));
+        }
+        Setting::validate(&archive.setting)
+            .map_err(|e| Error::Invalid(format!("Invalid setting: {e}")))?;
+        archive.history.validate()?;
+        Ok(Self { archive })
+    }
+
+    pub fn created(&self) -> DateTime<Local> {
+        // Checked by `read`
+        history::time_from_secs(self.archive.created)
+            .unwrap_or(UNIX_EPOCH)
+            .into()
+    }
+
+    pub fn record_count(&self) -> usize {
+        self.archive.history.record_count()
+    }
+
+    /// Replaces the history and the setting.
+    pub fn restore(&self, history: &mut History, setting: &mut Setting) -> Result<(), Error> {
+        history.restore_archive(&self.archive.history)?;
+        setting.restore(&self.archive.setting)
+    }
+}
+
+/// Writes a backup into `setting.backup_dir()` and removes the oldest ones
+/// over the limit. Returns the file path.
+pub fn back_up(history: &History, setting: &Setting) -> Result<PathBuf, Error> {
+    let dir = setting.backup_dir();
+    fs::create_dir_all(&dir)?;
+
+    let now = SystemTime::now();
+    let time: DateTime<Local> = now.into();
+    let mut file_path = dir.clone();
+    file_path.push(format!(
+        "{PREFIX}{}.{EXTENSION}",
+        time.format("%Y%m%d_%H%M%S")
+    ));
+    let archive = Archive {
+        version: Archive::VERSION,
+        created: now.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+        setting: setting.to_toml()?,
+        history: history.to_archive(),
+    };
+    // Write to a temporary file first, so a failure never leaves a broken backup.
+    let mut tmp_path = file_path.clone();
+    tmp_path.set_extension("tmp");
+    fs::write(&tmp_path, serde_json::to_vec(&archive)?)?;
+    fs::rename(&tmp_path, &file_path)?;
+
+    let backups = list(&dir);
+    let keep = setting.backup().keep.max(1);
+    if backups.len() > keep {
+        for path in backups[..backups.len() - keep].iter() {
+            fs::remove_file(path).ok();
+        }
+    }
+    Ok(file_path)
+}
+
+/// Backup files in `dir`, the oldest first.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == EXTENSION)
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(PREFIX))
+        })
+        .collect();
+    // The names contain the time
+    files.sort();
+    files
+}
+
+/// Backs up daily, on startup too unless the latest backup is newer than a day,
+/// so restarting often doesn't rotate the older backups out.
+pub struct Scheduler {
+    last: Option<Instant>,
+    /// The result of the last backup.
+    pub status: String,
+}
+
+impl Scheduler {
+    const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub fn new() -> Self {
+        Self {
+            last: None,
+            status: String::new(),
+        }
+    }
+
+    pub fn update(&mut self, ctx: &Context, history: &History, setting: &Setting) {
+        if self.last.is_none()
+            && let Some(path) = list(&setting.backup_dir()).pop()
+            && let Some(age) = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|time| time.elapsed().ok())
+            && age < Self::INTERVAL
+        {
+            self.last = Some(Instant::now().checked_sub(age).unwrap_or_else(Instant::now));
+            self.status = format!("Last backup: {}", path.display());
+        }
+        if let Some(last) = self.last
+            && last.elapsed() < Self::INTERVAL
+        {
+            ctx.request_repaint_after(Self::INTERVAL - last.elapsed());
+            return;
+        }
+        // The failure is shown in the status.
+        self.back_up(history, setting).ok();
+    }
+
+    pub fn back_up(&mut self, history: &History, setting: &Setting) -> Result<PathBuf, Error> {
+        self.last = Some(Instant::now());
+        let result = back_up(history, setting);
+        self.status = match &result {
+            Ok(path) => format!("Last backup: {}", path.display()),
+            Err(e) => format!("Backup failed: {e}"),
+        };
+        result
+    }
+}
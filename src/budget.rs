@@ -0,0 +1,94 @@
+use crate::{
+    calendar::Calendar,
+    history::History,
+    setting::{BudgetPeriod, TagBudget},
+};
+use chrono::{Datelike, Days, NaiveDate};
+use std::time::SystemTime;
+
+/// A budget is near to overrun once this part of it is used.
+const NEAR_RATIO: f32 = 0.9;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum BudgetState {
+    Normal,
+    Near,
+    Over,
+}
+
+/// The first day of the period containing `date`.
+pub fn period_start(period: BudgetPeriod, calendar: &Calendar, date: NaiveDate) -> NaiveDate {
+    match period {
+        BudgetPeriod::Week => calendar.week_start(date),
+        BudgetPeriod::Month => date.with_day(1).unwrap(),
+    }
+}
+
+pub struct BudgetUsage {
+    pub budget: TagBudget,
+    /// Seconds used in the current period.
+    pub used: u64,
+}
+
+impl BudgetUsage {
+    pub fn limit(&self) -> u64 {
+        self.budget.hours * 3600
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit().saturating_sub(self.used)
+    }
+
+    pub fn ratio(&self) -> f32 {
+        if self.limit() == 0 {
+            return 0.0;
+        }
+        self.used as f32 / self.limit() as f32
+    }
+
+    pub fn state(&self) -> BudgetState {
+        let ratio = self.ratio();
+        if ratio >= 1.0 {
+            BudgetState::Over
+        } else if ratio >= NEAR_RATIO {
+            BudgetState::Near
+        } else {
+            BudgetState::Normal
+        }
+    }
+}
+
+/// Usage of each budget in its current period.
+pub fn budget_usage(
+    history: &History,
+    calendar: &Calendar,
+    budgets: &[TagBudget],
+) -> Vec<BudgetUsage> {
+    let today = calendar.today();
+    let first = period_start(BudgetPeriod::Week, calendar, today).min(period_start(
+        BudgetPeriod::Month,
+        calendar,
+        today,
+    ));
+    // Records started the day before may cross into the period
+    let start = calendar.start_of(first - Days::new(1));
+    let records = history.get_records(&start, &SystemTime::now(), false);
+
+    budgets
+        .iter()
+        .map(|budget| {
+            let period_start = period_start(budget.period, calendar, today);
+            let used = records
+                .iter()
+                .filter(|r| r.tags.contains(&budget.tag))
+                .flat_map(|r| calendar.split(r.start_time, r.end_time, r.duration))
+                .filter(|(date, _)| *date >= period_start)
+                .map(|(_, secs)| secs)
+                .sum();
+            BudgetUsage {
+                budget: budget.clone(),
+                used,
+            }
+        })
+        .collect()
+}
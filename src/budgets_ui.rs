@@ -0,0 +1,91 @@
+use eframe::egui::{Grid, Id, Modal, ProgressBar, Ui, vec2};
+
+use crate::{
+    MyColor,
+    budget::{self, BudgetState, BudgetUsage},
+    history::History,
+    setting::Setting,
+};
+
+pub struct BudgetsWindow {
+    show: bool,
+    usage: Vec<BudgetUsage>,
+}
+
+impl BudgetsWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            usage: Vec::new(),
+        }
+    }
+
+    pub fn show(&mut self, history: &History, setting: &Setting) {
+        self.usage = budget::budget_usage(history, &setting.calendar(), setting.budgets());
+        self.usage
+            .sort_by_key(|u| setting.tags().iter().position(|t| t.name == u.budget.tag));
+        self.show = true;
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui) {
+        if self.show {
+            let modal = Modal::new(Id::new("budgets")).backdrop_color(MyColor::MODAL_BG);
+            let response = modal.show(ui.ctx(), |ui| {
+                ui.heading("Budgets");
+                ui.separator();
+
+                if self.usage.is_empty() {
+                    ui.label("No budget yet. Set budgets of the tags in Tags.");
+                    return;
+                }
+
+                let grid = Grid::new("budgets_grid")
+                    .striped(true)
+                    .spacing(vec2(12.0, 8.0));
+                grid.show(ui, |ui| {
+                    ui.strong("Tag");
+                    ui.strong("Period");
+                    ui.strong("Used");
+                    ui.strong("Remaining");
+                    ui.end_row();
+
+                    for usage in self.usage.iter() {
+                        ui.label(&usage.budget.tag);
+                        ui.label(format!(
+                            "This {}",
+                            usage.budget.period.name().to_lowercase()
+                        ));
+                        ui.label(format!(
+                            "{} / {} h",
+                            crate::hm_string(usage.used),
+                            usage.budget.hours
+                        ));
+                        let (color, text) = match usage.state() {
+                            BudgetState::Normal => {
+                                (MyColor::green(ui), crate::hm_string(usage.remaining()))
+                            }
+                            BudgetState::Near => {
+                                (MyColor::yellow(ui), crate::hm_string(usage.remaining()))
+                            }
+                            BudgetState::Over => (
+                                MyColor::red(ui),
+                                format!("Over {}", crate::hm_string(usage.used - usage.limit())),
+                            ),
+                        };
+                        ui.add(
+                            ProgressBar::new(usage.ratio())
+                                .desired_width(160.0)
+                                .fill(color)
+                                .text(text),
+                        );
+                        ui.end_row();
+                    }
+                });
+            });
+            if response.should_close() {
+                self.show = false;
+                self.usage.clear();
+            }
+        }
+    }
+}
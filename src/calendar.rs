@@ -0,0 +1,86 @@
+use chrono::{DateTime, Datelike, Days, Duration, Local, NaiveDate, NaiveTime, TimeZone, Weekday};
+use std::time::SystemTime;
+
+/// Maps times to working days. A day starts at `day_start` local time, so
+/// work after midnight can still belong to the previous day.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Calendar {
+    day_start: NaiveTime,
+    week_start: Weekday,
+}
+
+impl Default for Calendar {
+    fn default() -> Self {
+        Self::new(NaiveTime::MIN, Weekday::Mon)
+    }
+}
+
+impl Calendar {
+    pub fn new(day_start: NaiveTime, week_start: Weekday) -> Self {
+        Self {
+            day_start,
+            week_start,
+        }
+    }
+
+    /// The day which `time` belongs to.
+    pub fn date_of(&self, time: SystemTime) -> NaiveDate {
+        let local_time: DateTime<Local> = time.into();
+        (local_time.naive_local() - (self.day_start - NaiveTime::MIN)).date()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.date_of(SystemTime::now())
+    }
+
+    /// The time when `date` starts.
+    pub fn start_of(&self, date: NaiveDate) -> SystemTime {
+        let mut time = date.and_time(self.day_start);
+        loop {
+            if let Some(t) = Local.from_local_datetime(&time).earliest() {
+                return t.into();
+            }
+            // Skipped by a daylight saving change
+            time += Duration::minutes(30);
+        }
+    }
+
+    /// Splits the `duration` of a record over the days between `start` and
+    /// `end`, in proportion to the time the record spans in each day.
+    pub fn split(
+        &self,
+        start: SystemTime,
+        end: SystemTime,
+        duration: u64,
+    ) -> Vec<(NaiveDate, u64)> {
+        let mut date = self.date_of(start);
+        let last = self.date_of(end);
+        let span = end.duration_since(start).unwrap_or_default().as_secs_f64();
+        if date >= last || span <= 0.0 {
+            return vec![(date, duration)];
+        }
+
+        let mut rst = Vec::new();
+        let mut from = start;
+        let mut rest = duration;
+        while date < last {
+            let next = date + Days::new(1);
+            let to = self.start_of(next);
+            let secs = to.duration_since(from).unwrap_or_default().as_secs_f64();
+            let part = ((secs / span * duration as f64).round() as u64).min(rest);
+            rst.push((date, part));
+            rest -= part;
+            from = to;
+            date = next;
+        }
+        if rest > 0 {
+            rst.push((last, rest));
+        }
+        rst
+    }
+
+    /// The first day of the week containing `date`.
+    pub fn week_start(&self, date: NaiveDate) -> NaiveDate {
+        date - Days::new(date.weekday().days_since(self.week_start) as u64)
+    }
+}
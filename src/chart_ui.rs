@@ -1,14 +1,24 @@
-use crate::{MyColor, history::History};
-
-use chrono::{DateTime, Days, Local, NaiveDate};
+use crate::{
+    MyColor,
+    history::History,
+    setting::{self, Setting, TagLevel},
+    tags_ui,
+    time_range::TimeRange,
+};
+
+use chrono::{Days, NaiveDate};
 use eframe::egui::{Id, Modal, Ui};
-use egui_plot::{AxisHints, Bar, BarChart, Legend, Plot};
-use std::{collections::HashMap, time::SystemTime};
+use egui_plot::{AxisHints, Bar, BarChart, HLine, Legend, Plot};
+use std::collections::HashMap;
 
 pub struct ChartWindow {
     show: bool,
     records: Vec<(String, Vec<u64>)>,
-    time_window: TimeWindow,
+    /// The date of the rightmost bar.
+    last_date: NaiveDate,
+    level: TagLevel,
+    /// Parents having children in the records.
+    parents: Vec<String>,
 }
 
 impl ChartWindow {
@@ -16,13 +26,14 @@ impl ChartWindow {
         Self {
             show: false,
             records: Vec::new(),
-            time_window: TimeWindow::Day7,
+            last_date: NaiveDate::MIN,
+            level: TagLevel::Tag,
+            parents: Vec::new(),
         }
     }
 
-    pub fn show(&mut self, history: &History) {
-        self.time_window = TimeWindow::Day7;
-        self.refresh_records(history);
+    pub fn show(&mut self, history: &History, range: &TimeRange) {
+        self.refresh_records(history, range);
         self.show = true;
     }
 
@@ -31,36 +42,43 @@ impl ChartWindow {
         self.records = Vec::new();
     }
 
-    fn refresh_records(&mut self, history: &History) {
-        let end = SystemTime::now();
-        let start = match self.time_window {
-            TimeWindow::Day7 => crate::get_time_from_offset_days(-6),
-            TimeWindow::Day30 => crate::get_time_from_offset_days(-29),
-            TimeWindow::All => SystemTime::UNIX_EPOCH,
+    fn refresh_records(&mut self, history: &History, range: &TimeRange) {
+        // Records started the day before may cross into the range
+        let calendar = range.calendar();
+        let start = match range.from() {
+            Some(from) => calendar.start_of(from - Days::new(1)),
+            None => range.start(),
         };
-
-        let records = history.get_records(&start, &end, false);
+        let records = history.get_records(&start, &range.end(), false);
 
         // Statistic
-        let mut first_date = None;
+        let mut first_date = range.from();
+        let last_date = range.to();
         let mut tag_record_map: HashMap<&str, HashMap<NaiveDate, u64>> = HashMap::new();
         for record in records.iter() {
-            let local_time: DateTime<Local> = record.start_time.into();
-            let date = local_time.date_naive();
-            if first_date.is_none() {
-                first_date = Some(date);
+            let series = self.level.series(&record.tags);
+            for (date, duration) in
+                calendar.split(record.start_time, record.end_time, record.duration)
+            {
+                if first_date.is_some_and(|first| date < first) || date > last_date {
+                    continue;
+                }
+                if first_date.is_none() {
+                    first_date = Some(date);
+                }
+                for name in series.iter() {
+                    *tag_record_map
+                        .entry(name)
+                        .or_default()
+                        .entry(date)
+                        .or_default() += duration;
+                }
             }
-            tag_record_map
-                .entry(&record.tag)
-                .or_default()
-                .entry(date)
-                .and_modify(|duration| *duration += record.duration)
-                .or_insert(record.duration);
         }
 
         // Supplement zero
         if let Some(mut date) = first_date {
-            while date <= Local::now().date_naive() {
+            while date <= last_date {
                 for (_, date_duration_map) in tag_record_map.iter_mut() {
                     date_duration_map.entry(date).or_insert(0);
                 }
@@ -68,7 +86,10 @@ impl ChartWindow {
             }
         }
 
+        self.parents = setting::tag_parents(records.iter().flat_map(|r| r.tags.iter()));
+
         // Convert map to sorted vector
+        self.last_date = last_date;
         self.records.clear();
         let mut tags: Vec<_> = tag_record_map.keys().collect();
         tags.sort();
@@ -86,7 +107,7 @@ impl ChartWindow {
         }
     }
 
-    pub fn ui(&mut self, ui: &mut Ui, history: &History) {
+    pub fn ui(&mut self, ui: &mut Ui, history: &History, range: &mut TimeRange, setting: &Setting) {
         if self.show {
             let modal = Modal::new(Id::new("chart")).backdrop_color(MyColor::MODAL_BG);
             let response = modal.show(ui.ctx(), |ui| {
@@ -98,23 +119,11 @@ impl ChartWindow {
                 ui.horizontal(|ui| {
                     ui.heading("Chart");
                     ui.add_space(20.0);
-                    if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::Day7, "7 Days")
-                        .clicked()
-                    {
-                        self.refresh_records(history);
-                    }
-                    if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::Day30, "30 Days")
-                        .clicked()
-                    {
-                        self.refresh_records(history);
-                    }
-                    if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::All, "All")
-                        .clicked()
-                    {
-                        self.refresh_records(history);
+                    let mut changed = range.ui(ui);
+                    changed |=
+                        tags_ui::tag_level_ui(ui, "chart_level", &mut self.level, &self.parents);
+                    if changed {
+                        self.refresh_records(history, range);
                     }
                     ui.add_space(ui.available_width() - 30.0);
                     if ui.button("\u{274C}").clicked() {
@@ -123,38 +132,56 @@ impl ChartWindow {
                 });
                 ui.separator();
 
+                // A record counts for each of its series at these levels, stacking
+                // would add it up several times, so the bars are side by side.
+                let stacked = matches!(self.level, TagLevel::Children(_));
+                let width = if stacked {
+                    1.0
+                } else {
+                    1.0 / self.records.len().max(1) as f64
+                };
                 let mut charts = Vec::new();
-                for (tag, durations) in self.records.iter() {
+                for (k, (tag, durations)) in self.records.iter().enumerate() {
+                    let offset = if stacked {
+                        0.5
+                    } else {
+                        (k as f64 + 0.5) * width
+                    };
                     let bars = durations
                         .iter()
                         .enumerate()
-                        .map(|(i, duration)| Bar::new(-(i as f64) + 0.5, *duration as f64 / 3600.0))
+                        .map(|(i, duration)| {
+                            Bar::new(-(i as f64) + offset, *duration as f64 / 3600.0)
+                        })
                         .collect();
 
                     let name = tag.clone();
+                    let last_date = self.last_date;
                     let mut chart =
                         BarChart::new(tag, bars)
-                            .width(1.0)
+                            .width(width)
                             .element_formatter(Box::new(move |b, _| {
                                 format!(
                                     "{}\n{}\n{:.1} hours",
                                     name,
-                                    x_to_date(b.argument).format("%Y-%m-%d"),
+                                    x_to_date(last_date, b.argument).format("%Y-%m-%d"),
                                     b.value
                                 )
                             }));
-                    if !charts.is_empty() {
+                    if let Some(color) = setting.tag_color(tag) {
+                        chart = chart.color(color);
+                    }
+                    if stacked && !charts.is_empty() {
                         let others: Vec<&BarChart> = charts.iter().collect();
                         chart = chart.stack_on(&others);
                     }
                     charts.push(chart);
                 }
 
-                let x_axes = vec![
-                    AxisHints::new_x()
-                        .label("Date")
-                        .formatter(|mark, _| x_to_date(mark.value).format("%m-%d").to_string()),
-                ];
+                let last_date = self.last_date;
+                let x_axes = vec![AxisHints::new_x().label("Date").formatter(move |mark, _| {
+                    x_to_date(last_date, mark.value).format("%m-%d").to_string()
+                })];
                 let y_axes = vec![AxisHints::new_y().label("Hours")];
 
                 Plot::new("Stacked Bar Chart Demo")
@@ -164,10 +191,10 @@ impl ChartWindow {
                     .allow_drag(true)
                     .custom_x_axes(x_axes)
                     .custom_y_axes(y_axes)
-                    .label_formatter(|_, val| {
+                    .label_formatter(move |_, val| {
                         format!(
                             "{}\n{:.1} hours",
-                            x_to_date(val.x).format("%Y-%m-%d"),
+                            x_to_date(last_date, val.x).format("%Y-%m-%d"),
                             val.y
                         )
                     })
@@ -175,6 +202,10 @@ impl ChartWindow {
                         for c in charts {
                             plot_ui.bar_chart(c);
                         }
+                        let goal = setting.goal();
+                        if goal.daily > 0 {
+                            plot_ui.hline(HLine::new("Daily Goal", goal.daily as f64 / 60.0));
+                        }
                     })
                     .response
             });
@@ -185,15 +216,9 @@ impl ChartWindow {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum TimeWindow {
-    Day7,
-    Day30,
-    All,
-}
-
-fn x_to_date(x: f64) -> NaiveDate {
-    let mut date = Local::now().date_naive();
+/// The bar of `last_date` is drawn at x = 0.5.
+fn x_to_date(last_date: NaiveDate, x: f64) -> NaiveDate {
+    let mut date = last_date;
     if x < 0.0 {
         date = date
             .checked_sub_days(Days::new((-x + 0.99999) as u64))
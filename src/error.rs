@@ -0,0 +1,127 @@
+use crate::history::Record;
+use std::{fmt, path::PathBuf};
+
+/// Errors of the history and the setting storage.
+#[derive(Debug)]
+pub enum Error {
+    /// The OS has no config directory.
+    NoConfigDir,
+    Io(std::io::Error),
+    Db(sled::Error),
+    Toml(toml::de::Error),
+    TomlSer(toml::ser::Error),
+    Csv(csv::Error),
+    Json(serde_json::Error),
+    /// The record overlaps with this existing one.
+    Overlap(Box<Record>),
+    /// A backup which can't be restored.
+    Invalid(String),
+    /// The config file can't be parsed, it's kept as `backup`.
+    BrokenConfig {
+        line: usize,
+        column: usize,
+        message: String,
+        backup: PathBuf,
+    },
+}
+
+impl Error {
+    /// Locates the parse error `e` in `text`, lines and columns start from 1.
+    pub fn broken_config(text: &str, e: &toml::de::Error, backup: PathBuf) -> Self {
+        let (line, column) = e.span().map_or((0, 0), |span| {
+            let before = &text[..span.start.min(text.len())];
+            let line = before.matches('\n').count() + 1;
+            let column = before.chars().rev().take_while(|c| *c != '\n').count() + 1;
+            (line, column)
+        });
+        Error::BrokenConfig {
+            line,
+            column,
+            message: e.message().to_string(),
+            backup,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NoConfigDir => write!(f, "The config directory is not found"),
+            Error::Io(e) => write!(f, "{e}"),
+            Error::Db(e) => write!(f, "Database error: {e}"),
+            Error::Toml(e) => write!(f, "{}", e.message()),
+            Error::TomlSer(e) => write!(f, "{e}"),
+            Error::Csv(e) => write!(f, "{e}"),
+            Error::Json(e) => write!(f, "{e}"),
+            Error::Overlap(record) => {
+                let start: chrono::DateTime<chrono::Local> = record.start_time.into();
+                let end: chrono::DateTime<chrono::Local> = record.end_time.into();
+                write!(
+                    f,
+                    "It overlaps with the record from {} to {}.",
+                    start.format("%Y-%m-%d %H:%M:%S"),
+                    end.format("%H:%M:%S")
+                )
+            }
+            Error::Invalid(e) => write!(f, "{e}"),
+            Error::BrokenConfig {
+                line,
+                column,
+                message,
+                backup,
+            } => write!(
+                f,
+                "Line {line}, column {column}: {message}. The file is kept as {} \
+                 and the default setting is used.",
+                backup.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<sled::Error> for Error {
+    fn from(e: sled::Error) -> Self {
+        Error::Db(e)
+    }
+}
+
+impl From<sled::transaction::TransactionError<sled::Error>> for Error {
+    fn from(e: sled::transaction::TransactionError<sled::Error>) -> Self {
+        match e {
+            sled::transaction::TransactionError::Abort(e)
+            | sled::transaction::TransactionError::Storage(e) => Error::Db(e),
+        }
+    }
+}
+
+impl From<toml::de::Error> for Error {
+    fn from(e: toml::de::Error) -> Self {
+        Error::Toml(e)
+    }
+}
+
+impl From<toml::ser::Error> for Error {
+    fn from(e: toml::ser::Error) -> Self {
+        Error::TomlSer(e)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(e: csv::Error) -> Self {
+        Error::Csv(e)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
@@ -0,0 +1,125 @@
+use eframe::egui::{CentralPanel, Context, Id, Modal, RichText, Sides, Ui, ViewportCommand};
+
+use crate::{MyColor, error::Error, history::Record};
+
+/// An action which can be done again from the error window.
+#[derive(Clone)]
+pub enum Retry {
+    SaveSetting,
+    SaveCache,
+    AddRecord(Record),
+}
+
+#[derive(Clone)]
+struct Failure {
+    message: String,
+    retry: Option<Retry>,
+}
+
+fn failures_id() -> Id {
+    Id::new("error_failures")
+}
+
+/// Reports the error of `result` in the error window, so any window can
+/// report without owning it. Returns the value on success.
+pub fn check<T>(
+    ctx: &Context,
+    result: Result<T, Error>,
+    action: &str,
+    retry: Option<Retry>,
+) -> Option<T> {
+    match result {
+        Ok(v) => Some(v),
+        Err(e) => {
+            report(ctx, action, &e, retry);
+            None
+        }
+    }
+}
+
+pub fn report(ctx: &Context, action: &str, error: &Error, retry: Option<Retry>) {
+    let message = format!("{action} failed: {error}");
+    ctx.data_mut(|d| {
+        let failures = d.get_temp_mut_or_default::<Vec<Failure>>(failures_id());
+        // A repeating failure, e.g. of the heartbeat, is shown once. Each
+        // unsaved record is kept, even with the same message.
+        let keep = matches!(retry, Some(Retry::AddRecord(_)));
+        if keep || !failures.iter().any(|f| f.message == message) {
+            failures.push(Failure { message, retry });
+        }
+    });
+}
+
+pub fn has_failures(ctx: &Context) -> bool {
+    ctx.data(|d| {
+        d.get_temp::<Vec<Failure>>(failures_id())
+            .is_some_and(|f| !f.is_empty())
+    })
+}
+
+/// Shows the reported failures. Returns the action to retry, it's removed
+/// from the list and reported again if it fails again.
+pub fn ui(ui: &mut Ui) -> Option<Retry> {
+    let mut failures: Vec<Failure> = ui
+        .ctx()
+        .data(|d| d.get_temp(failures_id()))
+        .unwrap_or_default();
+    if failures.is_empty() {
+        return None;
+    }
+
+    let mut retry = None;
+    let mut remove = None;
+    let modal = Modal::new(Id::new("error")).backdrop_color(MyColor::MODAL_BG);
+    let response = modal.show(ui.ctx(), |ui| {
+        ui.set_width(400.0);
+        ui.heading("Something went wrong");
+        for (i, failure) in failures.iter().enumerate() {
+            ui.separator();
+            ui.label(RichText::new(&failure.message).color(ui.visuals().error_fg_color));
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Dismiss").clicked() {
+                        remove = Some(i);
+                    }
+                    if let Some(r) = failure.retry.as_ref()
+                        && ui.button("Retry").clicked()
+                    {
+                        retry = Some(r.clone());
+                        remove = Some(i);
+                    }
+                },
+            );
+        }
+    });
+    if response.should_close() {
+        failures.clear();
+    }
+    if let Some(i) = remove {
+        failures.remove(i);
+    }
+    ui.ctx()
+        .data_mut(|d| d.insert_temp(failures_id(), failures));
+    retry
+}
+
+/// The whole window when the app can't start. Returns true to retry.
+pub fn startup_ui(ctx: &Context, error: &Error) -> bool {
+    let mut retry = false;
+    CentralPanel::default().show(ctx, |ui| {
+        ui.heading("Work Timer can't start");
+        ui.add_space(10.0);
+        ui.label(RichText::new(error.to_string()).color(ui.visuals().error_fg_color));
+        ui.label("Another Work Timer may be running, or the config directory isn't writable.");
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            retry = ui.button("Retry").clicked();
+            if ui.button("Quit").clicked() {
+                ctx.send_viewport_cmd(ViewportCommand::Close);
+            }
+        });
+    });
+    retry
+}
@@ -0,0 +1,339 @@
+use crate::{
+    calendar::Calendar,
+    error::Error,
+    history::{self, Record},
+    setting::TagLevel,
+};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, Utc};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Column names of the CSV file, `History::import_from_csv` reads them back.
+pub const CSV_HEADER: [&str; 7] = ["start", "end", "duration", "tag", "timer", "pauses", "note"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+    ICalendar,
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 5] = [
+        Self::Csv,
+        Self::JsonLines,
+        Self::ICalendar,
+        Self::Markdown,
+        Self::Html,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Csv => "CSV",
+            Self::JsonLines => "JSON Lines",
+            Self::ICalendar => "iCalendar",
+            Self::Markdown => "Markdown Report",
+            Self::Html => "HTML Report",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::JsonLines => "jsonl",
+            Self::ICalendar => "ics",
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// `records` must be sorted by start time, `calendar` groups them by day and
+/// `level` by tag in reports.
+pub fn export(
+    records: &[Record],
+    format: ExportFormat,
+    calendar: &Calendar,
+    level: &TagLevel,
+    file_path: &Path,
+) -> Result<(), Error> {
+    match format {
+        ExportFormat::Csv => export_csv(records, file_path),
+        ExportFormat::JsonLines => export_json_lines(records, file_path),
+        ExportFormat::ICalendar => Ok(fs::write(file_path, to_icalendar(records))?),
+        ExportFormat::Markdown => Ok(fs::write(
+            file_path,
+            to_markdown(&Report::new(records, calendar, level)),
+        )?),
+        ExportFormat::Html => Ok(fs::write(
+            file_path,
+            to_html(&Report::new(records, calendar, level)),
+        )?),
+    }
+}
+
+fn export_csv(records: &[Record], file_path: &Path) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_path(file_path)?;
+    writer.write_record(CSV_HEADER)?;
+    for record in records {
+        writer.write_record(&[
+            to_iso_time(record.start_time.into()),
+            to_iso_time(record.end_time.into()),
+            record.duration.to_string(),
+            record.tags.join(&history::TAGS_SEPARATOR.to_string()),
+            record.timer.clone(),
+            record.pauses.to_string(),
+            record.note.clone(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    id: String,
+    start: String,
+    end: String,
+    duration: u64,
+    tags: &'a [String],
+    timer: &'a str,
+    pauses: u32,
+    note: &'a str,
+}
+
+fn export_json_lines(records: &[Record], file_path: &Path) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(file_path)?);
+    for record in records {
+        let json = JsonRecord {
+            id: record.id.to_string(),
+            start: to_iso_time(record.start_time.into()),
+            end: to_iso_time(record.end_time.into()),
+            duration: record.duration,
+            tags: &record.tags,
+            timer: &record.timer,
+            pauses: record.pauses,
+            note: &record.note,
+        };
+        serde_json::to_writer(&mut writer, &json)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn to_iso_time(time: DateTime<Local>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+// ----------------------------------------------------------------------------
+
+fn to_icalendar(records: &[Record]) -> String {
+    let stamp = ics_time(Utc::now());
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//Work Timer//EN".to_string(),
+    ];
+    for record in records {
+        let tags = record.tags.join(", ");
+        let summary = match (tags.is_empty(), record.timer.is_empty()) {
+            (false, false) => format!("{} ({})", tags, record.timer),
+            (false, true) => tags,
+            (true, false) => record.timer.clone(),
+            (true, true) => "Work".to_string(),
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@work-timer", record.id));
+        lines.push(format!("DTSTAMP:{stamp}"));
+        lines.push(format!("DTSTART:{}", ics_time(record.start_time.into())));
+        lines.push(format!("DTEND:{}", ics_time(record.end_time.into())));
+        lines.push(format!("SUMMARY:{}", ics_escape(&summary)));
+        if !record.tags.is_empty() {
+            let categories: Vec<String> = record.tags.iter().map(|t| ics_escape(t)).collect();
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+        if !record.note.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", ics_escape(&record.note)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut rst = String::new();
+    for line in lines {
+        ics_fold(&mut rst, &line);
+    }
+    rst
+}
+
+fn ics_time(time: DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Lines longer than 75 octets are folded, and every line ends with CRLF.
+fn ics_fold(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// ----------------------------------------------------------------------------
+
+/// Working time by tag, a record with several tags counts for each of them
+/// but only once in the total.
+#[derive(Default)]
+struct Tally {
+    tags: BTreeMap<String, u64>,
+    total: u64,
+}
+
+impl Tally {
+    fn add(&mut self, series: &[&str], duration: u64) {
+        for name in series {
+            *self.tags.entry(name.to_string()).or_default() += duration;
+        }
+        self.total += duration;
+    }
+}
+
+/// Working time grouped by day and tag.
+struct Report {
+    days: BTreeMap<NaiveDate, Tally>,
+    summary: Tally,
+}
+
+impl Report {
+    fn new(records: &[Record], calendar: &Calendar, level: &TagLevel) -> Self {
+        let mut days: BTreeMap<NaiveDate, Tally> = BTreeMap::new();
+        let mut summary = Tally::default();
+        for record in records {
+            let series = level.series(&record.tags);
+            if series.is_empty() {
+                continue;
+            }
+            for (date, duration) in
+                calendar.split(record.start_time, record.end_time, record.duration)
+            {
+                days.entry(date).or_default().add(&series, duration);
+            }
+            summary.add(&series, record.duration);
+        }
+        Self { days, summary }
+    }
+
+    fn title(&self) -> String {
+        match (self.days.keys().next(), self.days.keys().next_back()) {
+            (Some(first), Some(last)) if first != last => {
+                format!("Work Report {first} ~ {last}")
+            }
+            (Some(first), _) => format!("Work Report {first}"),
+            _ => "Work Report".to_string(),
+        }
+    }
+}
+
+fn hours_string(secs: u64) -> String {
+    format!("{} h {:02} m", secs / 3600, (secs % 3600) / 60)
+}
+
+fn to_markdown(report: &Report) -> String {
+    fn table(out: &mut String, tally: &Tally) {
+        out.push_str("| Tag | Time |\n| --- | ---: |\n");
+        for (tag, secs) in tally.tags.iter() {
+            let tag = tag.replace('|', "\\|");
+            writeln!(out, "| {} | {} |", tag, hours_string(*secs)).unwrap();
+        }
+        writeln!(out, "| **Total** | **{}** |\n", hours_string(tally.total)).unwrap();
+    }
+
+    let mut out = format!("# {}\n\n", report.title());
+    writeln!(
+        out,
+        "Total working time: {}\n",
+        hours_string(report.summary.total)
+    )
+    .unwrap();
+    out.push_str("## Summary\n\n");
+    table(&mut out, &report.summary);
+    for (date, tally) in report.days.iter() {
+        writeln!(out, "## {}\n", date.format("%Y-%m-%d %a")).unwrap();
+        table(&mut out, tally);
+    }
+    out
+}
+
+fn to_html(report: &Report) -> String {
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn table(out: &mut String, tally: &Tally) {
+        out.push_str("<table>\n<tr><th>Tag</th><th>Time</th></tr>\n");
+        for (tag, secs) in tally.tags.iter() {
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                escape(tag),
+                hours_string(*secs)
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "<tr><th>Total</th><th>{}</th></tr>\n</table>",
+            hours_string(tally.total)
+        )
+        .unwrap();
+    }
+
+    let title = escape(&report.title());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\nbody {{ font-family: sans-serif; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 1em; }}\n\
+         th, td {{ border: 1px solid #999; padding: 4px 12px; text-align: left; }}\n\
+         td:last-child, th:last-child {{ text-align: right; }}\n</style>\n\
+         </head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    writeln!(
+        out,
+        "<p>Total working time: {}</p>",
+        hours_string(report.summary.total)
+    )
+    .unwrap();
+    out.push_str("<h2>Summary</h2>\n");
+    table(&mut out, &report.summary);
+    for (date, tally) in report.days.iter() {
+        writeln!(out, "<h2>{}</h2>", date.format("%Y-%m-%d %a")).unwrap();
+        table(&mut out, tally);
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
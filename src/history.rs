@@ -1,38 +1,139 @@
+use crate::error::Error;
 use serde::{Deserialize, Serialize};
-use sled::{Db, IVec};
+use sled::{Batch, Db, IVec, Transactional, Tree, transaction::ConflictableTransactionResult};
 use std::{
-    path::PathBuf,
+    path::Path,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 pub struct History {
     db: Db,
+    session: Tree,
+    /// Deleted records by id, the value is the deletion time in seconds since
+    /// epoch followed by the record.
+    trash: Tree,
 }
 
 impl History {
-    pub fn new() -> Self {
-        let mut path = crate::setting::get_config_dir();
+    const SESSION_KEY: &str = "current";
+    const VERSION_KEY: &str = "version";
+    /// 1: seconds since epoch as key.
+    /// 2: nanoseconds since epoch and a unique sequence number as key.
+    /// 3: a list of tags instead of a single tag.
+    const VERSION: u8 = 3;
+
+    pub fn new() -> Result<Self, Error> {
+        let mut path = crate::setting::get_config_dir()?;
         path.push("history_db");
-        Self {
-            db: sled::open(&path).unwrap(),
+        let db = sled::open(&path)?;
+        let session = db.open_tree("session")?;
+        let trash = db.open_tree("trash")?;
+        Self::migrate(&db)?;
+        Ok(Self { db, session, trash })
+    }
+
+    fn migrate(db: &Db) -> Result<(), Error> {
+        let meta = db.open_tree("meta")?;
+        let version = meta
+            .get(Self::VERSION_KEY)?
+            .and_then(|v| v.first().copied())
+            .unwrap_or(1);
+        if version >= Self::VERSION {
+            return Ok(());
+        }
+
+        if version < 2 {
+            let mut batch = Batch::default();
+            for (key, value) in db.iter().flatten() {
+                if let Ok(array) = <[u8; 8]>::try_from(key.as_ref())
+                    && let Some(start_time) = time_from_secs(u64::from_be_bytes(array))
+                {
+                    let id = RecordId::new(&start_time, db.generate_id()?);
+                    batch.remove(key);
+                    batch.insert(&id.0, value);
+                }
+            }
+            db.apply_batch(batch)?;
         }
+        if version < 3 {
+            let mut batch = Batch::default();
+            for (key, value) in db.iter().flatten() {
+                if let Ok(value) = std::str::from_utf8(&value)
+                    && let Ok(mut record) = toml::from_str::<RecordTmp>(value)
+                {
+                    let tag = std::mem::take(&mut record.t);
+                    if !tag.is_empty() && record.g.is_empty() {
+                        record.g.push(tag);
+                    }
+                    batch.insert(key, toml::to_string(&record).unwrap().into_bytes());
+                }
+            }
+            db.apply_batch(batch)?;
+        }
+        meta.insert(Self::VERSION_KEY, &[Self::VERSION])?;
+        db.flush()?;
+        Ok(())
     }
 
-    pub fn add_record(&mut self, start_time: &SystemTime, duration: u64, tag: &str) {
-        let key = Self::to_key(start_time);
-        let record = RecordTmp {
-            d: duration,
-            t: tag.to_string(),
+    /// A new id is always assigned, `record.id` is ignored.
+    pub fn add_record(&mut self, record: &Record) -> Result<RecordId, Error> {
+        let id = RecordId::new(&record.start_time, self.db.generate_id()?);
+        self.db.insert(id.0, Self::to_value(record))?;
+        self.db.flush()?;
+        Ok(id)
+    }
+
+    /// Adds a record unless it overlaps with a neighbouring one, which is returned as error.
+    pub fn insert_record(&mut self, record: &Record) -> Result<RecordId, Error> {
+        if let Some(other) = self.find_overlap(record) {
+            return Err(Error::Overlap(Box::new(other)));
+        }
+        self.add_record(record)
+    }
+
+    /// Replaces the record with the same id, the id changes if the start time changes.
+    pub fn update_record(&mut self, record: &Record) -> Result<RecordId, Error> {
+        if let Some(other) = self.find_overlap(record) {
+            return Err(Error::Overlap(Box::new(other)));
+        }
+        let id = if record.id.start_time() == record.start_time {
+            record.id
+        } else {
+            RecordId::new(&record.start_time, self.db.generate_id()?)
         };
+        let mut batch = Batch::default();
+        batch.remove(&record.id.0);
+        batch.insert(&id.0, Self::to_value(record));
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(id)
+    }
+
+    fn find_overlap(&self, record: &Record) -> Option<Record> {
+        let start = RecordId::new(&record.start_time, 0).0;
+        let end = RecordId::new(&record.end_time, 0).0;
+        let previous = self
+            .db
+            .range(..start)
+            .rev()
+            .flatten()
+            .filter_map(|(key, value)| Self::to_record(key, value))
+            .find(|r| r.id != record.id);
+        if let Some(previous) = previous
+            && previous.end_time > record.start_time
+        {
+            return Some(previous);
+        }
         self.db
-            .insert(key, toml::to_string(&record).unwrap().as_bytes())
-            .ok();
-        self.db.flush().ok();
+            .range(start..end)
+            .flatten()
+            .filter_map(|(key, value)| Self::to_record(key, value))
+            .find(|r| r.id != record.id)
     }
 
     pub fn get_records(&self, start: &SystemTime, end: &SystemTime, reverse: bool) -> Vec<Record> {
-        let start = Self::to_key(start);
-        let end = Self::to_key(end);
+        let start = RecordId::new(start, 0).0;
+        let end = RecordId::new(end, 0).0;
         let mut rst = Vec::new();
         if reverse {
             for (key, value) in self.db.range(start..end).rev().flatten() {
@@ -52,80 +153,969 @@ impl History {
         rst
     }
 
-    pub fn remove(&mut self, key: &SystemTime) {
-        self.db.remove(Self::to_key(key)).ok();
-        self.db.flush().ok();
+    pub fn remove(&mut self, id: &RecordId) -> Result<(), Error> {
+        self.remove_records(&[*id])
     }
 
-    pub fn modify_tag(&mut self, key: &SystemTime, tag: &str) {
-        self.db
-            .fetch_and_update(Self::to_key(key), |value| {
-                if let Some(value) = value
-                    && let Ok(mut record) =
-                        toml::from_str::<RecordTmp>(std::str::from_utf8(value).unwrap())
-                {
-                    record.t = tag.to_string();
-                    return Some(toml::to_string(&record).unwrap().into_bytes());
+    /// Moves the records into the trash in one transaction.
+    pub fn remove_records(&mut self, ids: &[RecordId]) -> Result<(), Error> {
+        let deleted = Self::to_secs(&SystemTime::now()).to_be_bytes();
+        (&*self.db, &self.trash).transaction(
+            |(db, trash)| -> ConflictableTransactionResult<(), sled::Error> {
+                for id in ids {
+                    if let Some(value) = db.remove(&id.0)? {
+                        let mut entry = deleted.to_vec();
+                        entry.extend_from_slice(&value);
+                        trash.insert(&id.0, entry)?;
+                    }
                 }
-                None
+                Ok(())
+            },
+        )?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Deleted records with their deletion time, the latest deleted first.
+    pub fn trash_records(&self) -> Vec<(SystemTime, Record)> {
+        let mut rst: Vec<(SystemTime, Record)> = self
+            .trash
+            .iter()
+            .flatten()
+            .filter_map(|(key, value)| {
+                let deleted = u64::from_be_bytes(value.get(..8)?.try_into().ok()?);
+                let record = Self::to_record(key, value.subslice(8, value.len() - 8))?;
+                Some((time_from_secs(deleted)?, record))
+            })
+            .collect();
+        rst.sort_by_key(|(deleted, _)| std::cmp::Reverse(*deleted));
+        rst
+    }
+
+    /// Moves the records back from the trash in one transaction.
+    pub fn restore_records(&mut self, ids: &[RecordId]) -> Result<(), Error> {
+        (&*self.db, &self.trash).transaction(
+            |(db, trash)| -> ConflictableTransactionResult<(), sled::Error> {
+                for id in ids {
+                    if let Some(entry) = trash.remove(&id.0)?
+                        && entry.len() > 8
+                    {
+                        db.insert(&id.0, &entry[8..])?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Deletes the records in the trash permanently.
+    pub fn purge_records(&mut self, ids: &[RecordId]) -> Result<(), Error> {
+        let mut batch = Batch::default();
+        for id in ids {
+            batch.remove(&id.0);
+        }
+        self.trash.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// Deletes the records in the trash for longer than `days` permanently.
+    pub fn purge_expired(&mut self, days: u64) -> Result<(), Error> {
+        let limit =
+            Self::to_secs(&SystemTime::now()).saturating_sub(days.saturating_mul(24 * 60 * 60));
+        let ids: Vec<RecordId> = self
+            .trash_records()
+            .into_iter()
+            .filter(|(deleted, _)| Self::to_secs(deleted) < limit)
+            .map(|(_, record)| record.id)
+            .collect();
+        self.purge_records(&ids)
+    }
+
+    /// Sets the tags of the records in one batch, returns the old tags to
+    /// undo it with `restore_tags`.
+    pub fn retag_records(
+        &mut self,
+        ids: &[RecordId],
+        tags: &[String],
+    ) -> Result<Vec<(RecordId, Vec<String>)>, Error> {
+        self.set_tags(ids.iter().map(|id| (*id, tags)))
+    }
+
+    pub fn restore_tags(&mut self, old: &[(RecordId, Vec<String>)]) -> Result<(), Error> {
+        self.set_tags(old.iter().map(|(id, tags)| (*id, tags.as_slice())))
+            .map(|_| ())
+    }
+
+    fn set_tags<'a>(
+        &mut self,
+        changes: impl Iterator<Item = (RecordId, &'a [String])>,
+    ) -> Result<Vec<(RecordId, Vec<String>)>, Error> {
+        let mut batch = Batch::default();
+        let mut old = Vec::new();
+        for (id, tags) in changes {
+            if let Ok(Some(value)) = self.db.get(id.0)
+                && let Ok(value) = std::str::from_utf8(&value)
+                && let Ok(mut record) = toml::from_str::<RecordTmp>(value)
+            {
+                old.push((id, std::mem::replace(&mut record.g, tags.to_vec())));
+                batch.insert(&id.0, toml::to_string(&record).unwrap().into_bytes());
+            }
+        }
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(old)
+    }
+
+    /// Joins the records into the first one in one batch. Returns `None` if
+    /// there are less than two or another record is between them.
+    pub fn merge_records(&mut self, ids: &[RecordId]) -> Result<Option<RecordId>, Error> {
+        let mut ids = ids.to_vec();
+        ids.sort_by_key(|id| id.0);
+        ids.dedup();
+        if ids.len() < 2 {
+            return Ok(None);
+        }
+        let records: Vec<Record> = self
+            .db
+            .range(ids[0].0..=ids[ids.len() - 1].0)
+            .flatten()
+            .filter_map(|(key, value)| Self::to_record(key, value))
+            .collect();
+        if !records.iter().map(|r| r.id).eq(ids.iter().copied()) {
+            return Ok(None);
+        }
+
+        let mut merged = records[0].clone();
+        let mut batch = Batch::default();
+        for record in records[1..].iter() {
+            merged.append(record);
+            batch.remove(&record.id.0);
+        }
+        batch.insert(&merged.id.0, Self::to_value(&merged));
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(Some(merged.id))
+    }
+
+    pub fn modify_tags(&mut self, id: &RecordId, tags: &[String]) -> Result<(), Error> {
+        self.modify(id, |record| record.g = tags.to_vec())
+    }
+
+    pub fn modify_note(&mut self, id: &RecordId, note: &str) -> Result<(), Error> {
+        self.modify(id, |record| record.m = note.to_string())
+    }
+
+    /// The number of records tagged `tag`.
+    pub fn count_tag(&self, tag: &str) -> usize {
+        self.db
+            .iter()
+            .flatten()
+            .filter(|(_, value)| {
+                std::str::from_utf8(value)
+                    .ok()
+                    .and_then(|value| toml::from_str::<RecordTmp>(value).ok())
+                    .is_some_and(|record| record.g.iter().any(|t| t == tag))
             })
-            .ok();
-        self.db.flush().ok();
+            .count()
     }
 
-    pub fn export_to_csv(&self, file_path: PathBuf) {
-        let mut writer = csv::Writer::from_path(file_path).unwrap();
-        for record in self.get_records(&SystemTime::UNIX_EPOCH, &SystemTime::now(), true) {
-            let dt = record
-                .start_time
-                .duration_since(SystemTime::UNIX_EPOCH)
-                .unwrap();
-            writer
-                .write_record(&[
-                    dt.as_secs().to_string(),
-                    record.duration.to_string(),
-                    record.tag,
-                ])
-                .unwrap();
+    /// Replaces `from` with `to` in every record tagged `from` in one batch,
+    /// returns the number of changed records.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize, Error> {
+        let mut batch = Batch::default();
+        let mut count = 0;
+        for (key, value) in self.db.iter().flatten() {
+            if let Ok(value) = std::str::from_utf8(&value)
+                && let Ok(mut record) = toml::from_str::<RecordTmp>(value)
+                && record.g.iter().any(|t| t == from)
+            {
+                let mut tags = Vec::new();
+                for tag in record.g.iter() {
+                    let tag = if tag == from { to } else { tag };
+                    if !tags.iter().any(|t: &String| t == tag) {
+                        tags.push(tag.to_string());
+                    }
+                }
+                record.g = tags;
+                batch.insert(key, toml::to_string(&record).unwrap().into_bytes());
+                count += 1;
+            }
         }
-        writer.flush().unwrap();
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(count)
+    }
+
+    fn modify(&mut self, id: &RecordId, f: impl Fn(&mut RecordTmp)) -> Result<(), Error> {
+        self.db.fetch_and_update(id.0, |value| {
+            if let Some(value) = value
+                && let Ok(value) = std::str::from_utf8(value)
+                && let Ok(mut record) = toml::from_str::<RecordTmp>(value)
+            {
+                f(&mut record);
+                return Some(toml::to_string(&record).unwrap().into_bytes());
+            }
+            None
+        })?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// The records and the trash as they are stored, for backups.
+    pub fn to_archive(&self) -> HistoryArchive {
+        let entry = |key: &[u8], deleted, value: &[u8]| ArchiveEntry {
+            id: key.iter().map(|b| format!("{b:02x}")).collect(),
+            deleted,
+            value: String::from_utf8_lossy(value).into_owned(),
+        };
+        HistoryArchive {
+            version: Self::VERSION,
+            records: self
+                .db
+                .iter()
+                .flatten()
+                .map(|(key, value)| entry(&key, None, &value))
+                .collect(),
+            trash: self
+                .trash
+                .iter()
+                .flatten()
+                .filter(|(_, value)| value.len() > 8)
+                .map(|(key, value)| {
+                    let deleted = u64::from_be_bytes(value[..8].try_into().unwrap());
+                    entry(&key, Some(deleted), &value[8..])
+                })
+                .collect(),
+        }
+    }
+
+    /// Replaces all records and the trash with the archive, which is migrated
+    /// if it's from an older version.
+    pub fn restore_archive(&mut self, archive: &HistoryArchive) -> Result<(), Error> {
+        archive.validate()?;
+        let decode = |entry: &ArchiveEntry| {
+            let mut value = entry
+                .deleted
+                .map_or(Vec::new(), |d| d.to_be_bytes().to_vec());
+            value.extend_from_slice(entry.value.as_bytes());
+            (decode_hex(&entry.id).unwrap(), value)
+        };
+        let old_records: Vec<IVec> = self.db.iter().keys().flatten().collect();
+        let old_trash: Vec<IVec> = self.trash.iter().keys().flatten().collect();
+        (&*self.db, &self.trash).transaction(
+            |(db, trash)| -> ConflictableTransactionResult<(), sled::Error> {
+                for key in old_records.iter() {
+                    db.remove(key)?;
+                }
+                for key in old_trash.iter() {
+                    trash.remove(key)?;
+                }
+                for (key, value) in archive.records.iter().map(decode) {
+                    db.insert(key, value)?;
+                }
+                for (key, value) in archive.trash.iter().map(decode) {
+                    trash.insert(key, value)?;
+                }
+                Ok(())
+            },
+        )?;
+        let meta = self.db.open_tree("meta")?;
+        meta.insert(Self::VERSION_KEY, &[archive.version])?;
+        Self::migrate(&self.db)?;
+        self.db.flush()?;
+        Ok(())
+    }
+
+    /// The unfinished session left behind by a crash, if any.
+    pub fn session(&self) -> Option<Session> {
+        let value = self.session.get(Self::SESSION_KEY).ok()??;
+        toml::from_str(std::str::from_utf8(&value).ok()?).ok()
+    }
+
+    pub fn save_session(&mut self, session: &Session) -> Result<(), Error> {
+        self.session
+            .insert(Self::SESSION_KEY, toml::to_string(session)?.as_bytes())?;
+        self.session.flush()?;
+        Ok(())
+    }
+
+    pub fn clear_session(&mut self) -> Result<(), Error> {
+        self.session.remove(Self::SESSION_KEY)?;
+        self.session.flush()?;
+        Ok(())
+    }
+
+    /// Reads a file written by the CSV export and checks it against the existing records.
+    pub fn import_from_csv(&self, file_path: &Path) -> Result<CsvImport, Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(file_path)?;
+
+        let mut rst = CsvImport::default();
+        let mut columns = CsvColumns::LEGACY;
+        for (i, row) in reader.records().enumerate() {
+            let row = match row {
+                Ok(row) => row,
+                Err(e) => {
+                    let line = e.position().map(|p| p.line()).unwrap_or_default();
+                    rst.errors.push((line, e.to_string()));
+                    continue;
+                }
+            };
+            let line = row.position().map(|p| p.line()).unwrap_or_default();
+            if i == 0
+                && let Some(header) = CsvColumns::from_header(&row)
+            {
+                columns = header;
+                continue;
+            }
+            match Self::parse_csv_row(&row, &columns) {
+                Ok(record) => {
+                    let duplicate = self.find_same_second(&record.start_time);
+                    rst.rows.push(ImportRow {
+                        line,
+                        record,
+                        duplicate,
+                    });
+                }
+                Err(e) => rst.errors.push((line, e)),
+            }
+        }
+        Ok(rst)
+    }
+
+    /// Returns the number of records written.
+    pub fn apply_import(&mut self, import: &CsvImport, conflict: Conflict) -> Result<usize, Error> {
+        let mut batch = Batch::default();
+        let mut count = 0;
+        for row in import.rows.iter() {
+            let record = match (row.duplicate.as_ref(), conflict) {
+                (None, _) => {
+                    let id = RecordId::new(&row.record.start_time, self.db.generate_id()?);
+                    batch.insert(&id.0, Self::to_value(&row.record));
+                    count += 1;
+                    continue;
+                }
+                (Some(_), Conflict::Skip) => continue,
+                (Some(old), Conflict::Overwrite) => Record {
+                    id: old.id,
+                    start_time: old.start_time,
+                    ..row.record.clone()
+                },
+                (Some(old), Conflict::Merge) => old.merge(&row.record),
+            };
+            batch.insert(&record.id.0, Self::to_value(&record));
+            count += 1;
+        }
+        self.db.apply_batch(batch)?;
+        self.db.flush()?;
+        Ok(count)
+    }
+
+    fn parse_csv_row(row: &csv::StringRecord, columns: &CsvColumns) -> Result<Record, String> {
+        let field = |i: Option<usize>| i.and_then(|i| row.get(i)).unwrap_or_default().trim();
+        // Exported times are whole seconds, out of range ones are invalid.
+        let parse_time = |i: Option<usize>, name: &str| {
+            let text = field(i);
+            let secs = match text.parse::<u64>() {
+                Ok(secs) => Some(secs),
+                Err(_) => chrono::DateTime::parse_from_rfc3339(text)
+                    .ok()
+                    .and_then(|time| u64::try_from(time.timestamp()).ok()),
+            };
+            secs.and_then(time_from_secs)
+                .ok_or_else(|| format!("Invalid {name} \"{text}\""))
+        };
+
+        let start_time = parse_time(Some(columns.start), "start time")?;
+        let duration = field(Some(columns.duration))
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid duration \"{}\"", field(Some(columns.duration))))?;
+        let pauses = match field(columns.pauses) {
+            "" => 0,
+            v => v
+                .parse()
+                .map_err(|_| format!("Invalid pause count \"{v}\""))?,
+        };
+        let end_time = match field(columns.end) {
+            "" => Self::to_secs(&start_time)
+                .checked_add(duration)
+                .and_then(time_from_secs)
+                .ok_or_else(|| format!("The duration \"{duration}\" is too long"))?,
+            _ => parse_time(columns.end, "end time")?,
+        };
+        if end_time < start_time {
+            return Err("The end time is before the start time".to_string());
+        }
+        Ok(Record {
+            id: RecordId::default(),
+            start_time,
+            end_time,
+            duration,
+            tags: split_tags(field(Some(columns.tag))),
+            pauses,
+            timer: field(columns.timer).to_string(),
+            note: columns
+                .note
+                .and_then(|i| row.get(i))
+                .unwrap_or_default()
+                .to_string(),
+        })
+    }
+
+    /// Exported files only keep whole seconds, so this is how a duplicate is recognized.
+    fn find_same_second(&self, start_time: &SystemTime) -> Option<Record> {
+        let secs = Self::to_secs(start_time);
+        let start = RecordId::new(&time_from_secs(secs)?, 0).0;
+        let end = RecordId::new(&time_from_secs(secs.checked_add(1)?)?, 0).0;
+        self.db
+            .range(start..end)
+            .flatten()
+            .find_map(|(key, value)| Self::to_record(key, value))
     }
 
     fn to_record(key: IVec, value: IVec) -> Option<Record> {
         if let Ok(value) = std::str::from_utf8(value.as_ref())
             && let Ok(t) = toml::from_str::<RecordTmp>(value)
         {
-            let array: [u8; 8] = key.as_ref().try_into().unwrap();
-            let start_time_u64 = u64::from_be_bytes(array);
-            let start_time = UNIX_EPOCH + Duration::from_secs(start_time_u64);
+            let id = RecordId(key.as_ref().try_into().ok()?);
+            let start_time = id.start_time();
+            // Old records have no end time, so derive it from the duration.
+            let end_time = match t.e {
+                Some(e) => time_from_secs(e)?,
+                None => time_from_secs(Self::to_secs(&start_time).checked_add(t.d)?)?,
+            };
             return Some(Record {
+                id,
                 start_time,
+                end_time,
                 duration: t.d,
-                tag: t.t,
+                tags: t.g,
+                pauses: t.p,
+                timer: t.n,
+                note: t.m,
             });
         }
         None
     }
 
-    fn to_key(start_time: &SystemTime) -> [u8; 8] {
-        start_time
+    fn to_value(record: &Record) -> Vec<u8> {
+        let record = RecordTmp {
+            d: record.duration,
+            t: String::new(),
+            g: record.tags.clone(),
+            p: record.pauses,
+            n: record.timer.clone(),
+            e: Some(Self::to_secs(&record.end_time)),
+            m: record.note.clone(),
+        };
+        toml::to_string(&record).unwrap().into_bytes()
+    }
+
+    fn to_secs(time: &SystemTime) -> u64 {
+        time.duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+}
+
+/// Nanoseconds since epoch followed by a sequence number, both big endian,
+/// so that records are sorted by start time and never collide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct RecordId([u8; 16]);
+
+impl RecordId {
+    /// Record times are limited by `time_from_secs`, a time out of it is
+    /// clamped, which only happens to the ends of a range.
+    fn new(start_time: &SystemTime, seq: u64) -> Self {
+        let nanos = start_time
             .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_secs()
-            .to_be_bytes()
+            .unwrap_or_default()
+            .as_nanos();
+        let nanos = u64::try_from(nanos).unwrap_or(u64::MAX);
+        let mut key = [0; 16];
+        key[..8].copy_from_slice(&nanos.to_be_bytes());
+        key[8..].copy_from_slice(&seq.to_be_bytes());
+        Self(key)
+    }
+
+    fn start_time(&self) -> SystemTime {
+        let nanos = u64::from_be_bytes(self.0[..8].try_into().unwrap());
+        UNIX_EPOCH + Duration::from_nanos(nanos)
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug)]
+impl std::fmt::Display for RecordId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for b in self.0 {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Record {
+    pub id: RecordId,
     pub start_time: SystemTime,
+    pub end_time: SystemTime,
     pub duration: u64,
-    pub tag: String,
+    pub tags: Vec<String>,
+    pub pauses: u32,
+    pub timer: String,
+    pub note: String,
+}
+
+impl Record {
+    /// Keeps this record and fills its empty fields from the other one.
+    fn merge(&self, other: &Record) -> Record {
+        let mut record = self.clone();
+        if record.tags.is_empty() {
+            record.tags = other.tags.clone();
+        }
+        if record.timer.is_empty() {
+            record.timer = other.timer.clone();
+        }
+        record.merge_note(&other.note);
+        record
+    }
+
+    /// Extends this record to the end of the following one.
+    fn append(&mut self, other: &Record) {
+        self.end_time = self.end_time.max(other.end_time);
+        self.duration += other.duration;
+        self.pauses += other.pauses;
+        for tag in other.tags.iter() {
+            if !self.tags.contains(tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+        if self.timer.is_empty() {
+            self.timer = other.timer.clone();
+        }
+        self.merge_note(&other.note);
+    }
+
+    fn merge_note(&mut self, note: &str) {
+        if self.note.is_empty() {
+            self.note = note.to_string();
+        } else if !note.is_empty() && note != self.note {
+            self.note = format!("{}\n{}", self.note, note);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HistoryArchive {
+    version: u8,
+    records: Vec<ArchiveEntry>,
+    #[serde(default)]
+    trash: Vec<ArchiveEntry>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ArchiveEntry {
+    /// The key in hex
+    id: String,
+    /// Deletion time in seconds since epoch, only in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deleted: Option<u64>,
+    value: String,
+}
+
+impl HistoryArchive {
+    pub fn record_count(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Checks that every entry can be read by `History`.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.version == 0 || self.version > History::VERSION {
+            return Err(Error::Invalid(format!(
+                "Unsupported history version {}",
+                self.version
+            )));
+        }
+        let key_len = if self.version == 1 { 8 } else { 16 };
+        for entry in self.records.iter().chain(self.trash.iter()) {
+            let Some(key) = decode_hex(&entry.id).filter(|key| key.len() == key_len) else {
+                return Err(Error::Invalid(format!(
+                    "Invalid record id \"{}\"",
+                    entry.id
+                )));
+            };
+            let record = toml::from_str::<RecordTmp>(&entry.value).map_err(|e| {
+                Error::Invalid(format!("Invalid record {}: {}", entry.id, e.message()))
+            })?;
+            // The key starts with seconds in version 1, and nanoseconds later.
+            let start = u64::from_be_bytes(key[..8].try_into().unwrap_or_default());
+            let start = if key_len == 8 {
+                start
+            } else {
+                start / 1_000_000_000
+            };
+            let end = record.e.or_else(|| start.checked_add(record.d));
+            if time_from_secs(start).is_none() || end.and_then(time_from_secs).is_none() {
+                return Err(Error::Invalid(format!("Invalid time of {}", entry.id)));
+            }
+        }
+        if let Some(entry) = self
+            .trash
+            .iter()
+            .find(|e| e.deleted.and_then(time_from_secs).is_none())
+        {
+            return Err(Error::Invalid(format!(
+                "Invalid deletion time of {}",
+                entry.id
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// `None` if the time is after the year 2554, which a record id can't hold.
+pub fn time_from_secs(secs: u64) -> Option<SystemTime> {
+    if secs > u64::MAX / 1_000_000_000 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.is_ascii() || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Tags are joined by `TAGS_SEPARATOR` in a single CSV column.
+pub const TAGS_SEPARATOR: char = ';';
+
+pub fn split_tags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in text.split(TAGS_SEPARATOR).map(str::trim) {
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+#[derive(Default)]
+pub struct CsvImport {
+    pub rows: Vec<ImportRow>,
+    /// Line number and message of malformed lines.
+    pub errors: Vec<(u64, String)>,
+}
+
+pub struct ImportRow {
+    pub line: u64,
+    pub record: Record,
+    /// The existing record starting in the same second.
+    pub duplicate: Option<Record>,
+}
+
+/// Column indexes of a CSV file.
+struct CsvColumns {
+    start: usize,
+    duration: usize,
+    tag: usize,
+    pauses: Option<usize>,
+    end: Option<usize>,
+    timer: Option<usize>,
+    note: Option<usize>,
+}
+
+impl CsvColumns {
+    /// Files exported without a header: start, duration, tag, pauses, end, timer, note.
+    const LEGACY: Self = Self {
+        start: 0,
+        duration: 1,
+        tag: 2,
+        pauses: Some(3),
+        end: Some(4),
+        timer: Some(5),
+        note: Some(6),
+    };
+
+    fn from_header(row: &csv::StringRecord) -> Option<Self> {
+        let find = |name: &str| row.iter().position(|field| field.trim() == name);
+        let [start, end, duration, tag, timer, pauses, note] = crate::export::CSV_HEADER;
+        Some(Self {
+            start: find(start)?,
+            duration: find(duration)?,
+            tag: find(tag)?,
+            pauses: find(pauses),
+            end: find(end),
+            timer: find(timer),
+            note: find(note),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Conflict {
+    Skip,
+    Overwrite,
+    Merge,
 }
 
 #[derive(Deserialize, Serialize)]
 struct RecordTmp {
     d: u64,
+    /// The single tag before version 3
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     t: String,
+    /// Tags
+    #[serde(default)]
+    g: Vec<String>,
+    #[serde(default)]
+    p: u32,
+    /// Timer name
+    #[serde(default)]
+    n: String,
+    /// End time in seconds since epoch
+    #[serde(default)]
+    e: Option<u64>,
+    /// Note
+    #[serde(default)]
+    m: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Session {
+    pub timer: String,
+    pub start_time: SystemTime,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub active_secs: u64,
+    pub pauses: u32,
+    pub paused: bool,
+    pub heartbeat: SystemTime,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn parse(fields: &[&str], columns: &CsvColumns) -> Result<Record, String> {
+        History::parse_csv_row(&csv::StringRecord::from(fields.to_vec()), columns)
+    }
+
+    fn temporary_history() -> History {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        History {
+            session: db.open_tree("session").unwrap(),
+            trash: db.open_tree("trash").unwrap(),
+            db,
+        }
+    }
+
+    #[test]
+    fn parse_legacy_row() {
+        let fields = ["1000", "60", "A; B", "2", "", "Work", "a note"];
+        let record = parse(&fields, &CsvColumns::LEGACY).unwrap();
+        assert_eq!(record.start_time, secs(1000));
+        assert_eq!(record.end_time, secs(1060));
+        assert_eq!(record.duration, 60);
+        assert_eq!(record.tags, ["A", "B"]);
+        assert_eq!(record.pauses, 2);
+        assert_eq!(record.timer, "Work");
+        assert_eq!(record.note, "a note");
+    }
+
+    #[test]
+    fn parse_row_with_header() {
+        let header = csv::StringRecord::from(crate::export::CSV_HEADER.to_vec());
+        let columns = CsvColumns::from_header(&header).unwrap();
+        let fields = [
+            "1970-01-01T01:16:40+01:00",
+            "1970-01-01T00:20:00Z",
+            "150",
+            "A",
+            "",
+            "",
+            "",
+        ];
+        let record = parse(&fields, &columns).unwrap();
+        assert_eq!(record.start_time, secs(1000));
+        assert_eq!(record.end_time, secs(1200));
+        assert_eq!(record.pauses, 0);
+    }
+
+    #[test]
+    fn parse_invalid_rows() {
+        let max = u64::MAX.to_string();
+        let rows = [
+            // Out of range start times
+            ["18446744073", "60", "", "", "", "", ""],
+            [&max, "60", "", "", "", "", ""],
+            ["1960-01-01T00:00:00Z", "60", "", "", "", "", ""],
+            // Too long without an end time
+            ["1000", &max, "", "", "", "", ""],
+            ["1000", "x", "", "", "", "", ""],
+            ["1000", "60", "", "x", "", "", ""],
+            // The end is before the start
+            ["1000", "60", "", "", "999", "", ""],
+        ];
+        for fields in rows {
+            assert!(parse(&fields, &CsvColumns::LEGACY).is_err(), "{fields:?}");
+        }
+    }
+
+    #[test]
+    fn apply_import() {
+        let path =
+            std::env::temp_dir().join(format!("work_timer_import_{}.csv", std::process::id()));
+        // The first row is in the same second as the existing record, the second one isn't.
+        std::fs::write(&path, "1000,60,New,0,,Work,new\n1001,60,Other,0,,,\n").unwrap();
+
+        for (conflict, count) in [
+            (Conflict::Skip, 1),
+            (Conflict::Overwrite, 2),
+            (Conflict::Merge, 2),
+        ] {
+            let mut history = temporary_history();
+            let old = Record {
+                id: RecordId::default(),
+                start_time: secs(1000) + Duration::from_millis(500),
+                end_time: secs(1030),
+                duration: 30,
+                tags: vec!["Old".to_string()],
+                pauses: 0,
+                timer: String::new(),
+                note: "old".to_string(),
+            };
+            let old_id = history.add_record(&old).unwrap();
+
+            let import = history.import_from_csv(&path).unwrap();
+            assert!(import.errors.is_empty());
+            assert_eq!(import.rows.len(), 2);
+            assert!(
+                import.rows[0]
+                    .duplicate
+                    .as_ref()
+                    .is_some_and(|r| r.id == old_id)
+            );
+            assert!(import.rows[1].duplicate.is_none());
+
+            assert_eq!(history.apply_import(&import, conflict).unwrap(), count);
+            let records = history.get_records(&secs(0), &secs(2000), false);
+            assert_eq!(records.len(), 2);
+            let first = &records[0];
+            assert_eq!(first.id, old_id);
+            assert_eq!(first.start_time, old.start_time);
+            match conflict {
+                Conflict::Skip => {
+                    assert_eq!(first.tags, ["Old"]);
+                    assert_eq!(first.end_time, secs(1030));
+                }
+                Conflict::Overwrite => {
+                    assert_eq!(first.tags, ["New"]);
+                    assert_eq!(first.timer, "Work");
+                    assert_eq!(first.note, "new");
+                    assert_eq!(first.end_time, secs(1060));
+                }
+                Conflict::Merge => {
+                    assert_eq!(first.tags, ["Old"]);
+                    assert_eq!(first.timer, "Work");
+                    assert_eq!(first.note, "old\nnew");
+                    assert_eq!(first.end_time, secs(1030));
+                }
+            }
+            assert_eq!(records[1].tags, ["Other"]);
+        }
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn migrate_v1() {
+        let history = temporary_history();
+        // Version 1 keys are the start time in seconds, with a single tag.
+        let v1 = [
+            (3000u64, "d = 60\nt = \"B\"\n"),
+            (1000, "d = 120\nt = \"A\"\nn = \"Work\"\n"),
+            (2000, "d = 30\np = 2\n"),
+        ];
+        for (start, value) in v1 {
+            history.db.insert(start.to_be_bytes(), value).unwrap();
+        }
+
+        History::migrate(&history.db).unwrap();
+        let records = history.get_records(&secs(0), &secs(4000), false);
+        assert_eq!(records.len(), 3);
+        assert_eq!(history.db.len(), 3);
+
+        let starts: Vec<SystemTime> = records.iter().map(|r| r.start_time).collect();
+        assert_eq!(starts, [secs(1000), secs(2000), secs(3000)]);
+        for record in records.iter() {
+            assert_eq!(record.id.start_time(), record.start_time);
+        }
+        assert!(records[0].id != records[1].id && records[1].id != records[2].id);
+
+        assert_eq!(records[0].tags, ["A"]);
+        assert_eq!(records[0].end_time, secs(1120));
+        assert_eq!(records[0].timer, "Work");
+        assert!(records[1].tags.is_empty());
+        assert_eq!(records[1].pauses, 2);
+        assert_eq!(records[2].tags, ["B"]);
+        assert_eq!(records[2].duration, 60);
+
+        // Migrating again changes nothing.
+        History::migrate(&history.db).unwrap();
+        let again = history.get_records(&secs(0), &secs(4000), false);
+        let ids: Vec<RecordId> = again.iter().map(|r| r.id).collect();
+        assert_eq!(ids, records.iter().map(|r| r.id).collect::<Vec<_>>());
+        assert_eq!(again[0].tags, ["A"]);
+    }
+
+    #[test]
+    fn split_across_days() {
+        use crate::calendar::Calendar;
+        use chrono::{NaiveDate, NaiveTime, Weekday};
+
+        const HOUR: u64 = 60 * 60;
+        let calendar = Calendar::new(NaiveTime::from_hms_opt(4, 0, 0).unwrap(), Weekday::Mon);
+        let day = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let next = day.succ_opt().unwrap();
+        let after = next.succ_opt().unwrap();
+        let start_of_next = calendar.start_of(next);
+        let hours = |h: u64| Duration::from_secs(h * HOUR);
+
+        // From 23:00 to 2:00 crosses midnight but not the day start.
+        let start = start_of_next - hours(5);
+        assert_eq!(
+            calendar.split(start, start + hours(3), 3 * HOUR),
+            [(day, 3 * HOUR)]
+        );
+
+        // One boundary, the pauses are split in proportion.
+        let start = start_of_next - hours(2);
+        let end = start_of_next + hours(1);
+        assert_eq!(
+            calendar.split(start, end, 3 * HOUR),
+            [(day, 2 * HOUR), (next, HOUR)]
+        );
+        assert_eq!(
+            calendar.split(start, end, 3 * HOUR / 2),
+            [(day, HOUR), (next, HOUR / 2)]
+        );
+
+        // Two boundaries
+        let start = start_of_next - hours(1);
+        let end = calendar.start_of(after) + hours(1);
+        assert_eq!(
+            calendar.split(start, end, 26 * HOUR),
+            [(day, HOUR), (next, 24 * HOUR), (after, HOUR)]
+        );
+    }
 }
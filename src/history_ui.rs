@@ -1,21 +1,54 @@
-use std::time::SystemTime;
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant, SystemTime},
+};
 
 use crate::{
     MyColor,
-    history::{History, Record},
+    calendar::Calendar,
+    error_ui,
+    export::{self, ExportFormat},
+    history::{self, History, Record, RecordId},
+    import_ui::ImportWindow,
+    setting::{self, Setting, TagLevel},
+    setting_ui, tags_ui,
+    time_range::TimeRange,
+    trash_ui::TrashWindow,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime};
+use eframe::egui::{
+    Align2, Area, Button, ComboBox, Context, Frame, Grid, Id, Label, Modal, Order, RichText, Sense,
+    Sides, TextEdit, Ui, vec2,
 };
-use chrono::{DateTime, Local};
-use eframe::egui::{Id, Label, Modal, RichText, Sense, Sides, Ui};
-use egui_extras::{Column, TableBuilder};
+use egui_extras::{Column, DatePickerButton, TableBuilder};
 use rfd::FileDialog;
 
 pub struct HistoryWindow {
     show: bool,
+    /// All records in the time window, the latest first.
     records: Vec<Record>,
+    /// Indexes of the filtered records in display order.
+    view: Vec<usize>,
+    filter: RecordFilter,
+    sort: SortColumn,
+    descending: bool,
+    /// Selected records, only the ones in the view.
+    selected: HashSet<RecordId>,
+    /// Position in the view of the last clicked check box, shift-click selects from it.
+    anchor: Option<usize>,
+    bulk: Option<BulkAction>,
+    /// The last change, its message and when it was done.
+    undo: Option<(Undo, String, Instant)>,
+    trash_window: TrashWindow,
     delete_index: Option<usize>,
-    modify_index: Option<usize>,
-    modify_tag: String,
-    time_window: TimeWindow,
+    editor: Option<RecordEditor>,
+    import_window: ImportWindow,
+    export_format: ExportFormat,
+    /// How reports group the tags.
+    report_level: TagLevel,
+    message: String,
+    /// The records are changed in this frame.
+    changed: bool,
 }
 
 impl HistoryWindow {
@@ -23,35 +56,144 @@ impl HistoryWindow {
         Self {
             show: false,
             records: Vec::new(),
+            view: Vec::new(),
+            filter: RecordFilter::default(),
+            sort: SortColumn::Start,
+            descending: true,
+            selected: HashSet::new(),
+            anchor: None,
+            bulk: None,
+            undo: None,
+            trash_window: TrashWindow::new(),
             delete_index: None,
-            modify_index: None,
-            modify_tag: String::new(),
-            time_window: TimeWindow::Day1,
+            editor: None,
+            import_window: ImportWindow::new(),
+            export_format: ExportFormat::Csv,
+            report_level: TagLevel::Tag,
+            message: String::new(),
+            changed: false,
         }
     }
 
-    pub fn show(&mut self, history: &History) {
-        self.time_window = TimeWindow::Day1;
-        self.refresh_records(history);
+    pub fn show(&mut self, history: &History, range: &TimeRange) {
+        self.message.clear();
+        self.refresh_records(history, range);
         self.show = true;
     }
 
     fn close(&mut self) {
         self.show = false;
         self.records = Vec::new();
+        self.view = Vec::new();
+        self.selected.clear();
+        self.undo = None;
     }
 
-    fn refresh_records(&mut self, history: &History) {
-        let end = SystemTime::now();
-        let start = match self.time_window {
-            TimeWindow::Day1 => crate::get_time_from_offset_days(0),
-            TimeWindow::Day7 => crate::get_time_from_offset_days(-6),
-            TimeWindow::All => SystemTime::UNIX_EPOCH,
+    fn refresh_records(&mut self, history: &History, range: &TimeRange) {
+        self.records = history.get_records(&range.start(), &range.end(), true);
+        self.refresh_view();
+    }
+
+    /// Applies the filter and the sort order.
+    fn refresh_view(&mut self) {
+        let records = &self.records;
+        self.view = (0..records.len())
+            .filter(|i| self.filter.matches(&records[*i]))
+            .collect();
+        // The records are the latest first, a stable sort keeps that for equal
+        // keys in both orders.
+        let (sort, descending) = (self.sort, self.descending);
+        self.view.sort_by(|a, b| {
+            let (a, b) = if descending { (b, a) } else { (a, b) };
+            let (a, b) = (&records[*a], &records[*b]);
+            match sort {
+                SortColumn::Start => a.start_time.cmp(&b.start_time),
+                SortColumn::Duration => a.duration.cmp(&b.duration),
+                SortColumn::Tags => a.tags.join(", ").cmp(&b.tags.join(", ")),
+            }
+        });
+
+        let ids: HashSet<RecordId> = self.view.iter().map(|i| records[*i].id).collect();
+        self.selected.retain(|id| ids.contains(id));
+        self.anchor = None;
+    }
+
+    fn selected_ids(&self) -> Vec<RecordId> {
+        self.selected.iter().copied().collect()
+    }
+
+    /// Clicking the header sorts by `column`, clicking again reverses the order.
+    fn sort_header_ui(&mut self, ui: &mut Ui, text: &str, column: SortColumn) {
+        let text = match (self.sort == column, self.descending) {
+            (true, true) => format!("{text} \u{2B07}"),
+            (true, false) => format!("{text} \u{2B06}"),
+            (false, _) => text.to_string(),
         };
-        self.records = history.get_records(&start, &end, true);
+        if ui
+            .add(Label::new(RichText::new(text).strong()).sense(Sense::click()))
+            .on_hover_text("Sort")
+            .clicked()
+        {
+            if self.sort == column {
+                self.descending = !self.descending;
+            } else {
+                self.sort = column;
+                self.descending = column != SortColumn::Tags;
+            }
+            self.refresh_view();
+        }
     }
 
-    pub fn ui(&mut self, ui: &mut Ui, history: &mut History) {
+    /// Returns true if the filter is changed.
+    fn filter_ui(&mut self, ui: &mut Ui, setting: &Setting) -> bool {
+        let filter = &mut self.filter;
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Tags:");
+            let toggled =
+                tags_ui::tags_select_ui(ui, "history_filter_tags", 150.0, "All", setting, |tag| {
+                    filter.tags.contains(&tag.name)
+                })
+                .inner;
+            if let Some(tag) = toggled {
+                toggle_tag(&mut filter.tags, &tag.name);
+                changed = true;
+            }
+            ui.add_space(10.0);
+            changed |= ui
+                .add(
+                    TextEdit::singleline(&mut filter.text)
+                        .hint_text("Search tags and notes")
+                        .desired_width(160.0),
+                )
+                .changed();
+            ui.add_space(10.0);
+            ui.label("Duration:");
+            changed |= ui
+                .add(setting_ui::goal_drag_value(&mut filter.min_duration))
+                .on_hover_text("Minimum")
+                .changed();
+            ui.label("~");
+            changed |= ui
+                .add(setting_ui::goal_drag_value(&mut filter.max_duration))
+                .on_hover_text("Maximum")
+                .changed();
+            if !filter.is_empty() && ui.button("Clear").clicked() {
+                *filter = RecordFilter::default();
+                changed = true;
+            }
+        });
+        changed
+    }
+
+    /// Returns true if the records are changed.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        history: &mut History,
+        range: &mut TimeRange,
+        setting: &Setting,
+    ) -> bool {
         if self.show {
             let modal = Modal::new(Id::new("history")).backdrop_color(MyColor::MODAL_BG);
             let response = modal.show(ui.ctx(), |ui| {
@@ -62,76 +204,204 @@ impl HistoryWindow {
                 ui.horizontal(|ui| {
                     ui.heading("History");
                     ui.add_space(20.0);
-                    if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::Day1, "1 Day")
-                        .clicked()
+                    if range.ui(ui) {
+                        self.refresh_records(history, range);
+                    }
+                    ui.add_space(20.0);
+                    if ui.button("Add").clicked() {
+                        self.editor = Some(RecordEditor::new_record(self.records.first()));
+                    }
+                    if ui.button("Import CSV").clicked()
+                        && let Some(csv_file) =
+                            FileDialog::new().add_filter("CSV", &["csv"]).pick_file()
                     {
-                        self.refresh_records(history);
+                        self.import_window.show(history, &csv_file);
                     }
                     if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::Day7, "7 Days")
+                        .button("Trash")
+                        .on_hover_text("Restore deleted records.")
                         .clicked()
                     {
-                        self.refresh_records(history);
+                        self.trash_window.show(history);
+                    }
+                    ui.add_space(20.0);
+                    ComboBox::from_id_salt("export_format")
+                        .selected_text(self.export_format.name())
+                        .show_ui(ui, |ui| {
+                            for format in ExportFormat::ALL {
+                                ui.selectable_value(&mut self.export_format, format, format.name());
+                            }
+                        });
+                    if matches!(
+                        self.export_format,
+                        ExportFormat::Markdown | ExportFormat::Html
+                    ) {
+                        let parents =
+                            setting::tag_parents(self.records.iter().flat_map(|r| r.tags.iter()));
+                        tags_ui::tag_level_ui(ui, "report_level", &mut self.report_level, &parents);
                     }
                     if ui
-                        .selectable_value(&mut self.time_window, TimeWindow::All, "All")
+                        .button("Export")
+                        .on_hover_text("Export the filtered records in the selected time window.")
                         .clicked()
                     {
-                        self.refresh_records(history);
+                        self.export(range.calendar());
                     }
-                    ui.add_space(70.0);
-                    if ui.button("Export to CSV").clicked()
-                        && let Some(csv_file) =
-                            FileDialog::new().add_filter("CSV", &["csv"]).save_file()
-                    {
-                        history.export_to_csv(csv_file);
+                });
+                if !self.message.is_empty() {
+                    ui.label(&self.message);
+                }
+                if self.filter_ui(ui, setting) {
+                    self.refresh_view();
+                }
+                ui.horizontal(|ui| {
+                    let total: u64 = self.view.iter().map(|i| self.records[*i].duration).sum();
+                    ui.label(format!(
+                        "{} of {} records, total {}",
+                        self.view.len(),
+                        self.records.len(),
+                        crate::hm_string(total)
+                    ));
+                    if !self.selected.is_empty() {
+                        ui.add_space(20.0);
+                        self.selection_ui(ui, history, range);
                     }
                 });
                 ui.separator();
 
                 TableBuilder::new(ui)
                     .striped(true)
+                    .column(Column::auto())
                     .column(Column::remainder().at_least(180.0))
                     .column(Column::remainder().at_least(80.0))
+                    .column(Column::remainder().at_least(80.0))
+                    .column(Column::remainder().at_least(60.0))
                     .column(Column::remainder().at_least(40.0))
+                    .column(Column::remainder().at_least(100.0).clip(true))
                     .column(Column::remainder().at_least(40.0))
                     .header(20.0, |mut header| {
                         header.col(|ui| {
-                            ui.strong("Start Time");
+                            let mut all = !self.view.is_empty()
+                                && self
+                                    .view
+                                    .iter()
+                                    .all(|i| self.selected.contains(&self.records[*i].id));
+                            if ui
+                                .checkbox(&mut all, "")
+                                .on_hover_text("Select all")
+                                .changed()
+                            {
+                                self.selected.clear();
+                                if all {
+                                    self.selected
+                                        .extend(self.view.iter().map(|i| self.records[*i].id));
+                                }
+                                self.anchor = None;
+                            }
+                        });
+                        header.col(|ui| {
+                            self.sort_header_ui(ui, "Start Time", SortColumn::Start);
+                        });
+                        header.col(|ui| {
+                            ui.strong("End Time");
+                        });
+                        header.col(|ui| {
+                            self.sort_header_ui(ui, "Duration", SortColumn::Duration);
                         });
                         header.col(|ui| {
-                            ui.strong("Duration");
+                            ui.strong("Timer");
                         });
                         header.col(|ui| {
-                            ui.strong("Tag");
+                            self.sort_header_ui(ui, "Tags", SortColumn::Tags);
+                        });
+                        header.col(|ui| {
+                            ui.strong("Note");
                         });
                         header.col(|ui| {
                             ui.strong("Del");
                         });
                     })
                     .body(|body| {
-                        body.rows(18.0, self.records.len(), |mut row| {
-                            let index = row.index();
-                            let record = &self.records[row.index()];
+                        body.rows(18.0, self.view.len(), |mut row| {
+                            let position = row.index();
+                            let index = self.view[position];
+                            let record = &self.records[index];
+                            let mut clicked = false;
+                            let mut checked = self.selected.contains(&record.id);
+                            row.set_selected(checked);
+                            row.col(|ui| {
+                                if !ui.checkbox(&mut checked, "").changed() {
+                                    return;
+                                }
+                                let shift = ui.input(|i| i.modifiers.shift);
+                                match self.anchor.filter(|_| shift) {
+                                    Some(anchor) => {
+                                        for i in anchor.min(position)..=anchor.max(position) {
+                                            self.selected.insert(self.records[self.view[i]].id);
+                                        }
+                                    }
+                                    None if checked => {
+                                        self.selected.insert(record.id);
+                                    }
+                                    None => {
+                                        self.selected.remove(&record.id);
+                                    }
+                                }
+                                self.anchor = Some(position);
+                            });
                             row.col(|ui| {
                                 let local_time: DateTime<Local> = record.start_time.into();
                                 let text = local_time.format("%Y-%m-%d %H:%M:%S").to_string();
-                                ui.label(RichText::new(text).monospace());
+                                clicked |= ui
+                                    .add(
+                                        Label::new(RichText::new(text).monospace())
+                                            .sense(Sense::click()),
+                                    )
+                                    .clicked();
+                            });
+                            row.col(|ui| {
+                                let local_time: DateTime<Local> = record.end_time.into();
+                                let text = local_time.format("%H:%M:%S").to_string();
+                                clicked |= ui
+                                    .add(
+                                        Label::new(RichText::new(text).monospace())
+                                            .sense(Sense::click()),
+                                    )
+                                    .clicked();
                             });
                             row.col(|ui| {
                                 let text = crate::timer::secs_to_string(record.duration, "");
-                                ui.label(RichText::new(text).monospace());
+                                let mut response = ui.add(
+                                    Label::new(RichText::new(text).monospace())
+                                        .sense(Sense::click()),
+                                );
+                                if record.pauses > 0 {
+                                    response = response
+                                        .on_hover_text(format!("Paused {} times", record.pauses));
+                                }
+                                clicked |= response.clicked();
                             });
                             row.col(|ui| {
-                                if ui
-                                    .add(Label::new(&record.tag).sense(Sense::click()))
-                                    .clicked()
-                                {
-                                    self.modify_tag = record.tag.clone();
-                                    self.modify_index = Some(index);
+                                ui.label(&record.timer);
+                            });
+                            row.col(|ui| {
+                                for name in record.tags.iter() {
+                                    if let Some(tag) = setting.tag(name) {
+                                        ui.label(RichText::new("\u{25CF}").color(tag.color()));
+                                    }
+                                    clicked |=
+                                        ui.add(Label::new(name).sense(Sense::click())).clicked();
                                 }
                             });
+                            row.col(|ui| {
+                                clicked |= ui
+                                    .add(Label::new(&record.note).truncate().sense(Sense::click()))
+                                    .on_hover_text(&record.note)
+                                    .clicked();
+                            });
+                            if clicked {
+                                self.editor = Some(RecordEditor::new(record.clone(), false));
+                            }
                             row.col(|ui| {
                                 if ui.button("\u{274E}").clicked() {
                                     self.delete_index = Some(index);
@@ -144,7 +414,203 @@ impl HistoryWindow {
                 self.close();
             }
             self.delete_record_ui(ui, history);
-            self.modify_tag_ui(ui, history);
+            self.bulk_ui(ui, history, range, setting);
+            self.modify_record_ui(ui, history, range, setting);
+            if self.import_window.ui(ui, history) {
+                self.refresh_records(history, range);
+                self.changed = true;
+            }
+            if self.trash_window.ui(ui, history, setting.trash_days()) {
+                self.refresh_records(history, range);
+                self.changed = true;
+            }
+            self.undo_ui(ui.ctx(), history, range);
+        }
+        std::mem::take(&mut self.changed)
+    }
+
+    fn export(&mut self, calendar: &Calendar) {
+        let format = self.export_format;
+        if let Some(file_path) = FileDialog::new()
+            .add_filter(format.name(), &[format.extension()])
+            .set_file_name(format!("work_timer.{}", format.extension()))
+            .save_file()
+        {
+            let mut records: Vec<Record> =
+                self.view.iter().map(|i| self.records[*i].clone()).collect();
+            records.sort_by_key(|r| r.start_time);
+            self.message =
+                match export::export(&records, format, calendar, &self.report_level, &file_path) {
+                    Ok(()) => format!(
+                        "Exported {} records to {}",
+                        records.len(),
+                        file_path.display()
+                    ),
+                    Err(e) => format!("Export failed: {e}"),
+                };
+        }
+    }
+
+    /// Actions on the selected records.
+    fn selection_ui(&mut self, ui: &mut Ui, history: &mut History, range: &TimeRange) {
+        let count = self.selected.len();
+        ui.label(format!("{count} selected"));
+        if ui.button("Retag").clicked() {
+            self.bulk = Some(BulkAction::Retag(Vec::new()));
+        }
+        if ui
+            .add_enabled(count > 1, Button::new("Merge"))
+            .on_hover_text("Join adjacent records into the first one.")
+            .clicked()
+        {
+            let merged = history.merge_records(&self.selected_ids());
+            match error_ui::check(ui.ctx(), merged, "Merging", None) {
+                Some(Some(_)) => {
+                    self.message = format!("Merged {count} records");
+                    self.refresh_records(history, range);
+                    self.changed = true;
+                }
+                Some(None) => {
+                    self.message =
+                        "Only adjacent records without others between them can be merged"
+                            .to_string();
+                }
+                None => (),
+            }
+        }
+        if ui.button("Delete").clicked() {
+            self.bulk = Some(BulkAction::Delete);
+        }
+        if ui.button("Clear Selection").clicked() {
+            self.selected.clear();
+            self.anchor = None;
+        }
+    }
+
+    fn bulk_ui(
+        &mut self,
+        ui: &mut Ui,
+        history: &mut History,
+        range: &TimeRange,
+        setting: &Setting,
+    ) {
+        let Some(action) = self.bulk.as_mut() else {
+            return;
+        };
+
+        let count = self.selected.len();
+        let mut close = false;
+        let mut done = false;
+        let modal = Modal::new(Id::new("history_bulk")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            ui.set_width(350.0);
+            match action {
+                BulkAction::Delete => {
+                    ui.heading(format!("Are you sure you want to delete {count} records?"));
+                }
+                BulkAction::Retag(tags) => {
+                    ui.heading(format!("Retag {count} records"));
+                    ui.add_space(10.0);
+                    let toggled = tags_ui::tags_select_ui(
+                        ui,
+                        "history_bulk_tags",
+                        200.0,
+                        setting::UNTAGGED,
+                        setting,
+                        |tag| tags.contains(&tag.name),
+                    )
+                    .inner;
+                    if let Some(tag) = toggled {
+                        toggle_tag(tags, &tag.name);
+                    }
+                }
+            }
+            ui.add_space(32.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    let text = match action {
+                        BulkAction::Delete => "Yes",
+                        BulkAction::Retag(_) => "Save",
+                    };
+                    if ui.button(text).clicked() {
+                        let ids: Vec<RecordId> = self.selected.iter().copied().collect();
+                        let undo = match action {
+                            BulkAction::Delete => history
+                                .remove_records(&ids)
+                                .map(|_| (Undo::Delete(ids), format!("Deleted {count} records"))),
+                            BulkAction::Retag(tags) => {
+                                if tags.is_empty() {
+                                    tags.push(setting::UNTAGGED.to_string());
+                                }
+                                history.retag_records(&ids, tags).map(|old| {
+                                    (Undo::Retag(old), format!("Retagged {count} records"))
+                                })
+                            }
+                        };
+                        if let Some((undo, message)) =
+                            error_ui::check(ui.ctx(), undo, "Changing records", None)
+                        {
+                            self.undo = Some((undo, message, Instant::now()));
+                            self.changed = true;
+                        }
+                        done = true;
+                    }
+                    let text = match action {
+                        BulkAction::Delete => "No",
+                        BulkAction::Retag(_) => "Cancel",
+                    };
+                    if ui.button(text).clicked() {
+                        close = true;
+                    }
+                },
+            );
+        });
+        if done {
+            self.refresh_records(history, range);
+            self.selected.clear();
+        }
+        if done || close || response.should_close() {
+            self.bulk = None;
+        }
+    }
+
+    /// A toast to undo the last change for a while.
+    fn undo_ui(&mut self, ctx: &Context, history: &mut History, range: &TimeRange) {
+        const TIMEOUT: Duration = Duration::from_secs(10);
+        let Some((undo, message, time)) = self.undo.as_ref() else {
+            return;
+        };
+        let elapsed = time.elapsed();
+        if elapsed >= TIMEOUT {
+            self.undo = None;
+            return;
+        }
+        ctx.request_repaint_after(TIMEOUT - elapsed);
+
+        let mut clicked = false;
+        Area::new(Id::new("history_undo"))
+            .order(Order::Tooltip)
+            .anchor(Align2::CENTER_BOTTOM, vec2(0.0, -20.0))
+            .show(ctx, |ui| {
+                Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(message);
+                        ui.add_space(10.0);
+                        clicked = ui.button("Undo").clicked();
+                    });
+                });
+            });
+        if clicked {
+            let rst = match undo {
+                Undo::Delete(ids) => history.restore_records(ids),
+                Undo::Retag(old) => history.restore_tags(old),
+            };
+            error_ui::check(ctx, rst, "Undo", None);
+            self.undo = None;
+            self.changed = true;
+            self.refresh_records(history, range);
         }
     }
 
@@ -160,8 +626,19 @@ impl HistoryWindow {
                     |_ui| {},
                     |ui| {
                         if ui.button("Yes").clicked() {
-                            history.remove(&self.records[index].start_time);
-                            self.records.remove(index);
+                            let id = self.records[index].id;
+                            if error_ui::check(ui.ctx(), history.remove(&id), "Deleting", None)
+                                .is_some()
+                            {
+                                self.undo = Some((
+                                    Undo::Delete(vec![id]),
+                                    "Deleted 1 record".to_string(),
+                                    Instant::now(),
+                                ));
+                                self.records.remove(index);
+                                self.refresh_view();
+                                self.changed = true;
+                            }
                             self.delete_index = None;
                         }
 
@@ -177,45 +654,381 @@ impl HistoryWindow {
         }
     }
 
-    fn modify_tag_ui(&mut self, ui: &mut Ui, history: &mut History) {
-        if let Some(index) = self.modify_index {
-            let modal = Modal::new(Id::new("history_modify_tag")).backdrop_color(MyColor::MODAL_BG);
-            let response = modal.show(ui.ctx(), |ui| {
-                ui.heading("Modify Tag");
-                ui.add_space(10.0);
-                ui.horizontal(|ui| {
-                    ui.add_space(10.0);
-                    ui.text_edit_singleline(&mut self.modify_tag);
-                    ui.add_space(10.0);
-                });
-                ui.add_space(10.0);
+    fn modify_record_ui(
+        &mut self,
+        ui: &mut Ui,
+        history: &mut History,
+        range: &TimeRange,
+        setting: &Setting,
+    ) {
+        let Some(editor) = self.editor.as_mut() else {
+            return;
+        };
 
-                Sides::new().show(
-                    ui,
-                    |_ui| {},
-                    |ui| {
-                        if ui.button("Save").clicked() {
-                            history.modify_tag(&self.records[index].start_time, &self.modify_tag);
-                            self.records[index].tag = self.modify_tag.clone();
-                            self.modify_index = None;
-                        }
+        let mut close = false;
+        let mut saved = false;
+        let modal = Modal::new(Id::new("history_modify_record")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            ui.heading(if editor.is_new {
+                "Add Record"
+            } else {
+                "Modify Record"
+            });
+            ui.add_space(10.0);
+            Grid::new("history_modify_grid")
+                .spacing(vec2(8.0, 8.0))
+                .show(ui, |ui| {
+                    ui.label("Date:");
+                    if ui
+                        .add(DatePickerButton::new(&mut editor.date).id_salt("history_date"))
+                        .changed()
+                    {
+                        editor.update_end();
+                    }
+                    ui.end_row();
+
+                    ui.label("Start:");
+                    if ui.text_edit_singleline(&mut editor.start).lost_focus() {
+                        editor.update_end();
+                    }
+                    ui.end_row();
 
-                        if ui.button("Cancel").clicked() {
-                            self.modify_index = None;
+                    ui.label("Duration:");
+                    if ui
+                        .text_edit_singleline(&mut editor.duration)
+                        .on_hover_text("h:mm:ss")
+                        .lost_focus()
+                    {
+                        editor.update_end();
+                    }
+                    ui.end_row();
+
+                    ui.label("End:");
+                    if ui.text_edit_singleline(&mut editor.end).lost_focus() {
+                        editor.update_duration();
+                    }
+                    ui.end_row();
+
+                    ui.label("Tags:");
+                    ui.horizontal(|ui| {
+                        let toggled = tags_ui::tags_select_ui(
+                            ui,
+                            "history_tags",
+                            200.0,
+                            setting::UNTAGGED,
+                            setting,
+                            |tag| editor.tags.contains(&tag.name),
+                        )
+                        .inner;
+                        if let Some(tag) = toggled {
+                            toggle_tag(&mut editor.tags, &tag.name);
                         }
-                    },
-                );
-            });
-            if response.should_close() {
-                self.modify_index = None;
+                        // Tags which are deleted from the setting
+                        let mut removed = None;
+                        for name in editor.tags.iter().filter(|t| setting.tag(t).is_none()) {
+                            if ui
+                                .small_button(format!("{name} \u{274C}"))
+                                .on_hover_text("Remove")
+                                .clicked()
+                            {
+                                removed = Some(name.clone());
+                            }
+                        }
+                        if let Some(name) = removed {
+                            toggle_tag(&mut editor.tags, &name);
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Note:");
+                    ui.text_edit_multiline(&mut editor.note);
+                    ui.end_row();
+                });
+
+            if !editor.error.is_empty() {
+                ui.add_space(6.0);
+                ui.colored_label(ui.visuals().error_fg_color, &editor.error);
             }
+            ui.add_space(10.0);
+
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Save").clicked() {
+                        match editor.save(history) {
+                            Ok(()) => saved = true,
+                            Err(e) => editor.error = e,
+                        }
+                    }
+
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                },
+            );
+        });
+        if saved {
+            self.refresh_records(history, range);
+            self.changed = true;
+        }
+        if saved || close || response.should_close() {
+            self.editor = None;
         }
     }
 }
 
+/// A change which can be undone.
+enum Undo {
+    /// Records moved into the trash
+    Delete(Vec<RecordId>),
+    /// Records with their old tags
+    Retag(Vec<(RecordId, Vec<String>)>),
+}
+
+enum BulkAction {
+    Delete,
+    /// The new tags
+    Retag(Vec<String>),
+}
+
 #[derive(Clone, Copy, PartialEq)]
-enum TimeWindow {
-    Day1,
-    Day7,
-    All,
+enum SortColumn {
+    Start,
+    Duration,
+    Tags,
+}
+
+/// A record is shown if it matches every condition.
+#[derive(Default)]
+struct RecordFilter {
+    /// Any of them, no filtering if empty.
+    tags: Vec<String>,
+    /// Searched in the tags and the note, case insensitive.
+    text: String,
+    /// Minutes, 0 means no limit.
+    min_duration: u64,
+    max_duration: u64,
+}
+
+impl RecordFilter {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+            && self.text.trim().is_empty()
+            && self.min_duration == 0
+            && self.max_duration == 0
+    }
+
+    fn matches(&self, record: &Record) -> bool {
+        if !self.tags.is_empty() && !record.tags.iter().any(|t| self.tags.contains(t)) {
+            return false;
+        }
+        let text = self.text.trim().to_lowercase();
+        if !text.is_empty()
+            && !record.note.to_lowercase().contains(&text)
+            && !record.tags.iter().any(|t| t.to_lowercase().contains(&text))
+        {
+            return false;
+        }
+        if self.min_duration > 0 && record.duration < self.min_duration * 60 {
+            return false;
+        }
+        if self.max_duration > 0 && record.duration > self.max_duration * 60 {
+            return false;
+        }
+        true
+    }
+}
+
+struct RecordEditor {
+    record: Record,
+    is_new: bool,
+    date: NaiveDate,
+    start: String,
+    duration: String,
+    end: String,
+    tags: Vec<String>,
+    note: String,
+    error: String,
+}
+
+impl RecordEditor {
+    fn new(record: Record, is_new: bool) -> Self {
+        let start: DateTime<Local> = record.start_time.into();
+        let end: DateTime<Local> = record.end_time.into();
+        Self {
+            date: start.date_naive(),
+            start: start.format("%H:%M:%S").to_string(),
+            duration: format_duration(record.duration),
+            end: end.format("%H:%M:%S").to_string(),
+            tags: record.tags.clone(),
+            note: record.note.clone(),
+            error: String::new(),
+            record,
+            is_new,
+        }
+    }
+
+    /// A 25 minutes record ending now, tagged like the latest record.
+    fn new_record(latest: Option<&Record>) -> Self {
+        const DURATION: u64 = 25 * 60;
+        let end = SystemTime::now();
+        let record = Record {
+            id: RecordId::default(),
+            start_time: end - std::time::Duration::from_secs(DURATION),
+            end_time: end,
+            duration: DURATION,
+            tags: latest.map(|r| r.tags.clone()).unwrap_or_default(),
+            pauses: 0,
+            timer: String::new(),
+            note: String::new(),
+        };
+        Self::new(record, true)
+    }
+
+    fn start_time(&self) -> Option<DateTime<Local>> {
+        let time = parse_time(&self.start)?;
+        self.date
+            .and_time(time)
+            .and_local_timezone(Local)
+            .earliest()
+            .filter(in_range)
+    }
+
+    /// The end time is on the following day if it is before the start time.
+    fn end_time(&self) -> Option<DateTime<Local>> {
+        let start = self.start_time()?;
+        let time = parse_time(&self.end)?;
+        let mut end = self
+            .date
+            .and_time(time)
+            .and_local_timezone(Local)
+            .earliest()
+            .filter(in_range)?;
+        if end < start {
+            end += chrono::Duration::days(1);
+        }
+        Some(end)
+    }
+
+    fn update_end(&mut self) {
+        if let (Some(start), Some(duration)) = (self.start_time(), parse_duration(&self.duration))
+            && let Some(end) = i64::try_from(duration)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|d| start.checked_add_signed(d))
+        {
+            self.end = end.format("%H:%M:%S").to_string();
+        }
+    }
+
+    fn update_duration(&mut self) {
+        if let (Some(start), Some(end)) = (self.start_time(), self.end_time()) {
+            self.duration = format_duration((end - start).num_seconds().max(0) as u64);
+        }
+    }
+
+    fn to_record(&self) -> Result<Record, String> {
+        let start = self.start_time().ok_or("Invalid start time.")?;
+        let end = self.end_time().ok_or("Invalid end time.")?;
+        let duration = parse_duration(&self.duration).ok_or("Invalid duration.")?;
+        if duration == 0 {
+            return Err("The duration must be greater than zero.".to_string());
+        }
+        if duration > (end - start).num_seconds().max(0) as u64 {
+            return Err("The duration is longer than the time from start to end.".to_string());
+        }
+
+        let mut record = self.record.clone();
+        // Keep the sub-second part of unchanged times.
+        if start.timestamp() != DateTime::<Local>::from(record.start_time).timestamp() {
+            record.start_time = start.into();
+        }
+        if end.timestamp() != DateTime::<Local>::from(record.end_time).timestamp() {
+            record.end_time = end.into();
+        }
+        record.duration = duration;
+        // Like a record of the timer without a tag
+        record.tags = if self.tags.is_empty() {
+            vec![setting::UNTAGGED.to_string()]
+        } else {
+            self.tags.clone()
+        };
+        record.note = self.note.clone();
+        Ok(record)
+    }
+
+    fn save(&self, history: &mut History) -> Result<(), String> {
+        let record = self.to_record()?;
+        let old = &self.record;
+        let rst = if self.is_new {
+            history.insert_record(&record).map(|_| ())
+        } else if record.start_time == old.start_time
+            && record.end_time == old.end_time
+            && record.duration == old.duration
+        {
+            // Time is unchanged, so don't let overlapping neighbours block it.
+            let mut rst = Ok(());
+            if record.tags != old.tags {
+                rst = history.modify_tags(&record.id, &record.tags);
+            }
+            if rst.is_ok() && record.note != old.note {
+                rst = history.modify_note(&record.id, &record.note);
+            }
+            rst
+        } else {
+            history.update_record(&record).map(|_| ())
+        };
+
+        rst.map_err(|e| e.to_string())
+    }
+}
+
+fn toggle_tag(tags: &mut Vec<String>, name: &str) {
+    match tags.iter().position(|t| t == name) {
+        Some(i) => {
+            tags.remove(i);
+        }
+        None => tags.push(name.to_string()),
+    }
+}
+
+/// A record can't be before 1970 or too far in the future.
+fn in_range(time: &DateTime<Local>) -> bool {
+    u64::try_from(time.timestamp())
+        .ok()
+        .and_then(history::time_from_secs)
+        .is_some()
+}
+
+fn format_duration(secs: u64) -> String {
+    format!("{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+}
+
+/// Accepts "m", "h:mm" or "h:mm:ss".
+fn parse_duration(text: &str) -> Option<u64> {
+    let parts: Vec<u64> = text
+        .trim()
+        .split(':')
+        .map(|part| part.trim().parse().ok())
+        .collect::<Option<_>>()?;
+    // An overflow is invalid like any other typo.
+    let secs = |h: u64, m: u64, s: u64| {
+        h.checked_mul(3600)?
+            .checked_add(m.checked_mul(60)?)?
+            .checked_add(s)
+    };
+    match parts.as_slice() {
+        [m] => secs(0, *m, 0),
+        [h, m] => secs(*h, *m, 0),
+        [h, m, s] => secs(*h, *m, *s),
+        _ => None,
+    }
+}
+
+fn parse_time(text: &str) -> Option<NaiveTime> {
+    let text = text.trim();
+    NaiveTime::parse_from_str(text, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+        .ok()
 }
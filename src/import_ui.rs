@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use chrono::{DateTime, Local};
+use eframe::egui::{Button, Id, Modal, RichText, ScrollArea, Sides, Ui};
+use egui_extras::{Column, TableBuilder};
+
+use crate::{
+    MyColor, error_ui,
+    history::{Conflict, CsvImport, History},
+};
+
+pub struct ImportWindow {
+    import: Option<Result<CsvImport, String>>,
+    conflict: Conflict,
+}
+
+impl ImportWindow {
+    pub fn new() -> Self {
+        Self {
+            import: None,
+            conflict: Conflict::Skip,
+        }
+    }
+
+    pub fn show(&mut self, history: &History, file_path: &Path) {
+        self.import = Some(
+            history
+                .import_from_csv(file_path)
+                .map_err(|e| e.to_string()),
+        );
+        self.conflict = Conflict::Skip;
+    }
+
+    /// Returns true if records are imported.
+    pub fn ui(&mut self, ui: &mut Ui, history: &mut History) -> bool {
+        let Some(import) = self.import.as_ref() else {
+            return false;
+        };
+
+        let mut close = false;
+        let mut imported = false;
+        let modal = Modal::new(Id::new("history_import")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            if let Some(r) = crate::get_viewport_inner_rect(ui.ctx()) {
+                ui.set_max_height(r.height() - 45.0);
+            }
+            ui.heading("Import CSV");
+            ui.separator();
+
+            let import = match import {
+                Ok(import) => import,
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                    ui.add_space(10.0);
+                    Sides::new().show(
+                        ui,
+                        |_ui| {},
+                        |ui| {
+                            if ui.button("Close").clicked() {
+                                close = true;
+                            }
+                        },
+                    );
+                    return;
+                }
+            };
+
+            let duplicates = import.rows.iter().filter(|r| r.duplicate.is_some()).count();
+            ui.label(format!(
+                "{} records, {} duplicates, {} malformed lines",
+                import.rows.len(),
+                duplicates,
+                import.errors.len()
+            ));
+
+            if !import.errors.is_empty() {
+                ui.add_space(6.0);
+                ScrollArea::vertical()
+                    .id_salt("import_errors")
+                    .max_height(80.0)
+                    .show(ui, |ui| {
+                        for (line, e) in import.errors.iter() {
+                            ui.colored_label(
+                                ui.visuals().error_fg_color,
+                                format!("Line {line}: {e}"),
+                            );
+                        }
+                    });
+            }
+            ui.add_space(6.0);
+
+            ui.horizontal(|ui| {
+                ui.label("Duplicates:");
+                ui.selectable_value(&mut self.conflict, Conflict::Skip, "Skip")
+                    .on_hover_text("Keep the existing records.");
+                ui.selectable_value(&mut self.conflict, Conflict::Overwrite, "Overwrite")
+                    .on_hover_text("Replace the existing records.");
+                ui.selectable_value(&mut self.conflict, Conflict::Merge, "Merge")
+                    .on_hover_text("Keep the existing records and fill their empty fields.");
+            });
+            ui.separator();
+
+            ui.push_id("import_table", |ui| {
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .max_scroll_height(300.0)
+                    .column(Column::auto().at_least(40.0))
+                    .column(Column::auto().at_least(160.0))
+                    .column(Column::auto().at_least(80.0))
+                    .column(Column::auto().at_least(80.0))
+                    .column(Column::remainder().at_least(80.0))
+                    .header(20.0, |mut header| {
+                        header.col(|ui| {
+                            ui.strong("Line");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Start Time");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Duration");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Tags");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Status");
+                        });
+                    })
+                    .body(|body| {
+                        body.rows(18.0, import.rows.len(), |mut row| {
+                            let r = &import.rows[row.index()];
+                            row.col(|ui| {
+                                ui.label(r.line.to_string());
+                            });
+                            row.col(|ui| {
+                                let local_time: DateTime<Local> = r.record.start_time.into();
+                                let text = local_time.format("%Y-%m-%d %H:%M:%S").to_string();
+                                ui.label(RichText::new(text).monospace());
+                            });
+                            row.col(|ui| {
+                                let text = crate::timer::secs_to_string(r.record.duration, "");
+                                ui.label(RichText::new(text).monospace());
+                            });
+                            row.col(|ui| {
+                                ui.label(r.record.tags.join(", "));
+                            });
+                            row.col(|ui| {
+                                ui.label(if r.duplicate.is_some() {
+                                    "Duplicate"
+                                } else {
+                                    "New"
+                                });
+                            });
+                        });
+                    });
+            });
+            ui.add_space(10.0);
+
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui
+                        .add_enabled(!import.rows.is_empty(), Button::new("Import"))
+                        .clicked()
+                    {
+                        let applied = history.apply_import(import, self.conflict);
+                        imported = error_ui::check(ui.ctx(), applied, "Importing", None).is_some();
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                },
+            );
+        });
+
+        if imported || close || response.should_close() {
+            self.import = None;
+        }
+        imported
+    }
+}
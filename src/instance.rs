@@ -0,0 +1,83 @@
+use eframe::egui::{Context, ViewportCommand};
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use crate::error::Error;
+
+const LOCK_FILE: &str = "instance.lock";
+/// The port the running instance listens on, in a separate file because the
+/// lock file can't be read on Windows while it's locked.
+const PORT_FILE: &str = "instance.port";
+const MESSAGE: &[u8] = b"focus";
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Held by the only running instance, the lock is released when the process exits.
+pub struct Instance {
+    lock: File,
+    listener: TcpListener,
+}
+
+impl Instance {
+    /// Returns `None` if another instance is running, it's asked to raise its window.
+    pub fn acquire(dir: &Path) -> Result<Option<Self>, Error> {
+        let lock = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))?;
+        match lock.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => {
+                signal(dir);
+                return Ok(None);
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        fs::write(
+            dir.join(PORT_FILE),
+            listener.local_addr()?.port().to_string(),
+        )?;
+        Ok(Some(Self { lock, listener }))
+    }
+
+    /// Raises the window of `ctx` whenever another instance is started.
+    pub fn listen(self, ctx: Context) {
+        thread::spawn(move || {
+            let _lock = self.lock;
+            for mut stream in self.listener.incoming().flatten() {
+                // A client that sends nothing mustn't block the next one.
+                if stream.set_read_timeout(Some(TIMEOUT)).is_err() {
+                    continue;
+                }
+                let mut buf = [0; MESSAGE.len()];
+                if stream.read_exact(&mut buf).is_ok() && buf == MESSAGE {
+                    ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+                    ctx.send_viewport_cmd(ViewportCommand::Focus);
+                    ctx.request_repaint();
+                }
+            }
+        });
+    }
+}
+
+/// Best effort, the running instance may still be starting.
+fn signal(dir: &Path) {
+    let Some(port) = fs::read_to_string(dir.join(PORT_FILE))
+        .ok()
+        .and_then(|text| text.trim().parse::<u16>().ok())
+    else {
+        return;
+    };
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    if let Ok(mut stream) = TcpStream::connect_timeout(&addr, TIMEOUT) {
+        stream.write_all(MESSAGE).ok();
+    }
+}
@@ -1,36 +1,74 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
 mod audio;
+mod backup;
+mod budget;
+mod budgets_ui;
+mod calendar;
 mod chart_ui;
+mod error;
+mod error_ui;
+mod export;
 mod history;
 mod history_ui;
+mod import_ui;
+mod instance;
 mod left_panel_ui;
+mod recovery_ui;
+mod sequence;
+mod sequences_ui;
 mod setting;
 mod setting_ui;
 mod tags_ui;
+mod time_range;
 mod timer;
 mod timers_ui;
+mod trash_ui;
 
 use audio::Audio;
+use backup::Scheduler;
+use budget::{BudgetState, BudgetUsage};
+use budgets_ui::BudgetsWindow;
+use calendar::Calendar;
 use chart_ui::ChartWindow;
-use chrono::Local;
+use chrono::{Days, NaiveDate};
 use eframe::egui::{
-    self, Align, Button, CentralPanel, Color32, ComboBox, Context, FontId, Frame, Layout, RichText,
-    TextStyle, Theme, Ui, ViewportCommand, Visuals, WindowLevel, pos2, vec2,
+    self, Align, Button, CentralPanel, Color32, Context, FontId, Frame, InnerResponse, Layout,
+    ProgressBar, RichText, TextStyle, Theme, Ui, ViewportCommand, Visuals, WindowLevel, pos2, vec2,
 };
-use history::History;
+use error::Error;
+use error_ui::Retry;
+use history::{History, Record, RecordId, Session};
 use history_ui::HistoryWindow;
+use instance::Instance;
 use left_panel_ui::LeftPanel;
-use setting::Setting;
+use recovery_ui::{Recovery, RecoveryWindow};
+use sequence::{Advance, Sequencer};
+use sequences_ui::SequencesWindow;
+use setting::{BudgetPeriod, Setting};
 use setting_ui::SettingWindow;
-use std::{fs, path::PathBuf, time::SystemTime};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
 use tags_ui::TagsWindow;
+use time_range::TimeRange;
 use timer::{Status, Timer};
 use timers_ui::TimersWindow;
 
-use crate::setting::TimerSetting;
+use crate::setting::{SequenceSetting, TagSetting, TimerSetting, UNTAGGED};
 
 fn main() -> eframe::Result {
+    // A second instance only raises the window of the first one, so they never
+    // fight over the history and the setting files.
+    let instance = match setting::get_config_dir().and_then(|dir| Instance::acquire(&dir)) {
+        Ok(Some(instance)) => Some(instance),
+        Ok(None) => return Ok(()),
+        // Run unguarded, an unusable config directory is reported on startup.
+        Err(_) => None,
+    };
     let setting = Setting::new();
 
     let app_path = get_app_path();
@@ -38,13 +76,15 @@ fn main() -> eframe::Result {
     let icon = eframe::icon_data::from_png_bytes(&png_bytes).unwrap();
     let mut viewport = egui::ViewportBuilder::default()
         .with_min_inner_size([400.0, 330.0])
-        .with_icon(icon)
-        .with_maximized(setting.window_maximized());
+        .with_icon(icon);
 
-    if let Some(info) = setting.window_info() {
-        viewport = viewport
-            .with_position(pos2(info.x, info.y))
-            .with_inner_size(vec2(info.width, info.height));
+    if let Ok(setting) = &setting {
+        viewport = viewport.with_maximized(setting.window_maximized());
+        if let Some(info) = setting.window_info() {
+            viewport = viewport
+                .with_position(pos2(info.x, info.y))
+                .with_inner_size(vec2(info.width, info.height));
+        }
     }
 
     let options = eframe::NativeOptions {
@@ -55,10 +95,70 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "Work Timer",
         options,
-        Box::new(|cc| Ok(Box::new(MyEguiApp::new(cc, setting, app_path)))),
+        Box::new(|cc| {
+            if let Some(instance) = instance {
+                instance.listen(cc.egui_ctx.clone());
+            }
+            set_style(&cc.egui_ctx);
+            Ok(Box::new(Launcher::new(&cc.egui_ctx, setting, app_path)))
+        }),
     )
 }
 
+fn set_style(ctx: &Context) {
+    let style = eframe::egui::Style {
+        text_styles: [
+            (TextStyle::Heading, FontId::proportional(30.0)),
+            (TextStyle::Body, FontId::proportional(15.0)),
+            (TextStyle::Button, FontId::proportional(15.0)),
+            (TextStyle::Monospace, FontId::monospace(15.0)),
+        ]
+        .into(),
+        ..Default::default()
+    };
+    ctx.set_style_of(Theme::Dark, style.clone());
+    ctx.set_style_of(Theme::Light, style);
+
+    let mut v = Visuals::dark();
+    v.override_text_color = Some(Color32::from_rgb(240, 240, 240));
+    ctx.set_visuals_of(Theme::Dark, v);
+
+    let mut v = Visuals::light();
+    v.override_text_color = Some(Color32::from_rgb(20, 20, 20));
+    ctx.set_visuals_of(Theme::Light, v);
+}
+
+/// Shows why the app can't start instead of panicking, e.g. when the history
+/// is locked by another process, and lets the user retry.
+enum Launcher {
+    Running(Box<MyEguiApp>),
+    Failed { error: Error, app_path: PathBuf },
+}
+
+impl Launcher {
+    fn new(ctx: &Context, setting: Result<Setting, Error>, app_path: PathBuf) -> Self {
+        match setting.and_then(|setting| History::new().map(|history| (setting, history))) {
+            Ok((setting, history)) => {
+                Self::Running(Box::new(MyEguiApp::new(ctx, setting, history, app_path)))
+            }
+            Err(error) => Self::Failed { error, app_path },
+        }
+    }
+}
+
+impl eframe::App for Launcher {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        match self {
+            Self::Running(app) => app.update(ctx, frame),
+            Self::Failed { error, app_path } => {
+                if error_ui::startup_ui(ctx, error) {
+                    *self = Self::new(ctx, Setting::new(), app_path.clone());
+                }
+            }
+        }
+    }
+}
+
 fn get_app_path() -> PathBuf {
     #[cfg(debug_assertions)]
     let app_path = PathBuf::from("./");
@@ -85,11 +185,16 @@ struct MyEguiApp {
     left_panel: LeftPanel,
     setting: Setting,
     setting_window: SettingWindow,
+    backup_scheduler: Scheduler,
     history: History,
     history_window: HistoryWindow,
     chart_window: ChartWindow,
+    time_range: TimeRange,
     tags_window: TagsWindow,
     timers_window: TimersWindow,
+    sequences_window: SequencesWindow,
+    budgets_window: BudgetsWindow,
+    recovery_window: RecoveryWindow,
 }
 
 impl eframe::App for MyEguiApp {
@@ -98,25 +203,60 @@ impl eframe::App for MyEguiApp {
             let btn_status = self.left_panel.ui(ui);
             for btn in btn_status {
                 match btn {
-                    0 => self.chart_window.show(&self.history),
-                    1 => self.history_window.show(&self.history),
+                    0 => {
+                        self.time_range.refresh(self.setting.calendar());
+                        self.chart_window.show(&self.history, &self.time_range);
+                    }
+                    1 => {
+                        self.time_range.refresh(self.setting.calendar());
+                        self.history_window.show(&self.history, &self.time_range);
+                    }
                     2 => self.tags_window.show(),
                     3 => self.timers_window.show(&self.setting),
-                    4 => self.setting_window.show(),
+                    4 => self.sequences_window.show(&self.setting),
+                    5 => self.budgets_window.show(&self.history, &self.setting),
+                    6 => self.setting_window.show(&self.setting),
                     _ => (),
                 }
             }
 
+            self.backup_scheduler
+                .update(ctx, &self.history, &self.setting);
             self.main_panel
                 .ui(ctx, ui, &self.setting, &mut self.history);
-            self.chart_window.ui(ui, &self.history);
-            self.history_window.ui(ui, &mut self.history);
-            self.setting_window.ui(ui, &mut self.setting);
+            self.chart_window
+                .ui(ui, &self.history, &mut self.time_range, &self.setting);
+            if self
+                .history_window
+                .ui(ui, &mut self.history, &mut self.time_range, &self.setting)
+            {
+                self.main_panel.refresh_total_time(&self.history);
+            }
+            if self.setting_window.ui(
+                ui,
+                &mut self.setting,
+                &mut self.history,
+                &mut self.backup_scheduler,
+            ) {
+                self.main_panel.tag_ids = self.setting.tag_ids().to_vec();
+                self.main_panel.refresh_total_time(&self.history);
+            }
             if self.setting_window.is_show() {
                 self.main_panel.timer_panel.change_color(ui);
             }
-            self.tags_window.ui(ui, &mut self.setting);
+            if self
+                .tags_window
+                .ui(ui, &mut self.setting, &mut self.history)
+            {
+                self.main_panel.refresh_total_time(&self.history);
+            }
             self.timers_window.ui(ui, &mut self.setting);
+            self.sequences_window.ui(ui, &mut self.setting);
+            self.budgets_window.ui(ui);
+            self.recovery_ui(ui);
+            if let Some(retry) = error_ui::ui(ui) {
+                self.retry(ctx, retry);
+            }
             if ctx.input(|i| i.viewport().close_requested()) {
                 self.on_close(ctx);
             }
@@ -125,45 +265,25 @@ impl eframe::App for MyEguiApp {
 }
 
 impl MyEguiApp {
-    fn new(cc: &eframe::CreationContext<'_>, setting: Setting, app_path: PathBuf) -> Self {
-        // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
-        // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
-        // for e.g. egui::PaintCallback.
-
-        let style = eframe::egui::Style {
-            text_styles: [
-                (TextStyle::Heading, FontId::proportional(30.0)),
-                (TextStyle::Body, FontId::proportional(15.0)),
-                (TextStyle::Button, FontId::proportional(15.0)),
-                (TextStyle::Monospace, FontId::monospace(15.0)),
-            ]
-            .into(),
-            ..Default::default()
-        };
-        cc.egui_ctx.set_style_of(Theme::Dark, style.clone());
-        cc.egui_ctx.set_style_of(Theme::Light, style);
-
-        let mut v = Visuals::dark();
-        v.override_text_color = Some(Color32::from_rgb(240, 240, 240));
-        cc.egui_ctx.set_visuals_of(Theme::Dark, v);
-
-        let mut v = Visuals::light();
-        v.override_text_color = Some(Color32::from_rgb(20, 20, 20));
-        cc.egui_ctx.set_visuals_of(Theme::Light, v);
-
+    fn new(ctx: &Context, mut setting: Setting, mut history: History, app_path: PathBuf) -> Self {
         let setting_window = SettingWindow::new();
 
-        cc.egui_ctx.set_theme(setting.theme());
+        ctx.set_theme(setting.theme());
+        if let Some(e) = setting.take_load_error() {
+            error_ui::report(ctx, "Loading config.toml", &e, None);
+        }
 
-        let history = History::new();
+        error_ui::check(
+            ctx,
+            history.purge_expired(setting.trash_days()),
+            "Emptying the trash",
+            None,
+        );
+        let recovery_window = RecoveryWindow::new(history.session());
+        let time_range = TimeRange::new(setting.calendar());
 
         Self {
-            main_panel: MainPanel::new(
-                Self::init_total_time(&history),
-                setting.tag_index(),
-                app_path,
-            ),
+            main_panel: MainPanel::new(&history, setting.calendar(), setting.tag_ids(), app_path),
             left_panel: LeftPanel::new(
                 110.0,
                 &[
@@ -171,36 +291,116 @@ impl MyEguiApp {
                     ("\u{1F4C4}", "History"),
                     ("\u{1F3F7}", "Tags"),
                     ("\u{23F0}", "Timers"),
+                    ("\u{1F501}", "Cycles"),
+                    ("\u{1F4B0}", "Budgets"),
                     ("\u{26ED}", "Setting"),
                 ],
             ),
             setting,
             setting_window,
+            backup_scheduler: Scheduler::new(),
             history,
             history_window: HistoryWindow::new(),
             chart_window: ChartWindow::new(),
+            time_range,
             tags_window: TagsWindow::new(),
             timers_window: TimersWindow::new(),
+            sequences_window: SequencesWindow::new(),
+            budgets_window: BudgetsWindow::new(),
+            recovery_window,
         }
     }
 
-    fn init_total_time(history: &History) -> u64 {
-        let end = SystemTime::now();
-        let start = get_time_from_offset_days(0);
-        history
-            .get_records(&start, &end, false)
-            .iter()
-            .map(|r| r.duration)
-            .sum()
+    fn recovery_ui(&mut self, ui: &mut Ui) {
+        let can_resume = self
+            .recovery_window
+            .timer()
+            .is_some_and(|name| self.setting.timer_list().iter().any(|t| t.name == name));
+        match self.recovery_window.ui(ui, can_resume) {
+            Some(Recovery::Resume(session)) => {
+                self.main_panel.resume_session(&session, &self.setting);
+            }
+            Some(Recovery::Save(session)) => {
+                let tags = if session.tags.is_empty() {
+                    vec![UNTAGGED.to_string()]
+                } else {
+                    session.tags
+                };
+                let record = Record {
+                    id: RecordId::default(),
+                    start_time: session.start_time,
+                    end_time: session.heartbeat,
+                    duration: session.active_secs,
+                    tags,
+                    pauses: session.pauses,
+                    timer: session.timer,
+                    note: String::new(),
+                };
+                if self.add_record(ui.ctx(), record) {
+                    self.clear_session(ui.ctx());
+                }
+            }
+            Some(Recovery::Discard) => self.clear_session(ui.ctx()),
+            None => (),
+        }
+    }
+
+    /// Returns true if the record is saved.
+    fn add_record(&mut self, ctx: &Context, record: Record) -> bool {
+        let added = self.history.add_record(&record);
+        let added = error_ui::check(
+            ctx,
+            added,
+            "Saving the record",
+            Some(Retry::AddRecord(record)),
+        )
+        .is_some();
+        if added {
+            self.main_panel.refresh_total_time(&self.history);
+        }
+        added
+    }
+
+    fn clear_session(&mut self, ctx: &Context) {
+        let cleared = self.history.clear_session();
+        error_ui::check(ctx, cleared, "Clearing the session", None);
+    }
+
+    fn retry(&mut self, ctx: &Context, retry: Retry) {
+        match retry {
+            Retry::SaveSetting => {
+                let saved = self.setting.save();
+                error_ui::check(ctx, saved, "Saving the setting", Some(Retry::SaveSetting));
+            }
+            Retry::SaveCache => {
+                let saved = self.setting.save_cache();
+                error_ui::check(
+                    ctx,
+                    saved,
+                    "Saving the window state",
+                    Some(Retry::SaveCache),
+                );
+            }
+            Retry::AddRecord(record) => {
+                // The session of the record is kept until it's saved, unless
+                // another timer has started since.
+                if self.add_record(ctx, record) && self.main_panel.timer.status() == Status::Stopped
+                {
+                    self.clear_session(ctx);
+                }
+            }
+        }
     }
 
     fn on_close(&mut self, ctx: &Context) {
-        self.main_panel.stop(
-            &self.setting.tags()[self.main_panel.tag_index],
-            &mut self.history,
-        );
+        let reported = error_ui::has_failures(ctx);
+        // The crashed session stays until it's resolved in the recovery window.
+        if !self.recovery_window.is_pending() {
+            let tags = self.main_panel.tag_names(&self.setting);
+            self.main_panel.stop(ctx, &tags, &mut self.history);
+        }
 
-        self.setting.set_tag_index(self.main_panel.tag_index);
+        self.setting.set_tag_ids(self.main_panel.tag_ids.clone());
 
         // Save window info
         ctx.viewport(|v| {
@@ -220,7 +420,18 @@ impl MyEguiApp {
                 }
             }
         });
-        self.setting.save_cache();
+        let saved = self.setting.save_cache();
+        error_ui::check(
+            ctx,
+            saved,
+            "Saving the window state",
+            Some(Retry::SaveCache),
+        );
+
+        // Stay open to show what isn't saved, closing again discards it.
+        if !reported && error_ui::has_failures(ctx) {
+            ctx.send_viewport_cmd(ViewportCommand::CancelClose);
+        }
     }
 }
 
@@ -230,37 +441,124 @@ fn get_viewport_inner_rect(ctx: &Context) -> Option<egui::Rect> {
 
 // ----------------------------------------------------------------------------
 
+/// Working time of a tag in the current day, week and month.
+#[derive(Clone, Copy, Default)]
+struct TagTimes {
+    day: u64,
+    week: u64,
+    month: u64,
+}
+
 struct MainPanel {
     timer_panel: TimerPanel,
     total_time: u64,
+    week_time: u64,
+    tag_times: HashMap<String, TagTimes>,
+    /// The day `total_time` is counted for.
+    today: NaiveDate,
+    calendar: Calendar,
     timer: Timer,
+    sequencer: Sequencer,
     audio: Audio,
-    tag_index: usize,
+    /// The selected tag, see `TagSetting::id`.
+    tag_ids: Vec<u64>,
     on_top: bool,
     app_path: PathBuf,
+    session_changed: bool,
+    heartbeat: Instant,
 }
 
 impl MainPanel {
-    fn new(total_time: u64, tag_index: usize, app_path: PathBuf) -> Self {
-        Self {
-            total_time,
+    fn new(history: &History, calendar: Calendar, tag_ids: &[u64], app_path: PathBuf) -> Self {
+        let mut panel = Self {
+            total_time: 0,
+            week_time: 0,
+            tag_times: HashMap::new(),
+            today: calendar.today(),
+            calendar,
             timer: Timer::new(),
+            sequencer: Sequencer::new(),
             timer_panel: TimerPanel::new(),
             audio: Audio::new(),
-            tag_index,
+            tag_ids: tag_ids.to_vec(),
             on_top: false,
             app_path,
+            session_changed: false,
+            heartbeat: Instant::now(),
+        };
+        panel.refresh_total_time(history);
+        panel
+    }
+
+    /// Sums today's, this week's and this month's part of the records,
+    /// including records crossing the day boundary.
+    fn refresh_total_time(&mut self, history: &History) {
+        self.today = self.calendar.today();
+        let week_start = budget::period_start(BudgetPeriod::Week, &self.calendar, self.today);
+        let month_start = budget::period_start(BudgetPeriod::Month, &self.calendar, self.today);
+        let first = week_start.min(month_start);
+        let start = self.calendar.start_of(first - Days::new(1));
+
+        self.total_time = 0;
+        self.week_time = 0;
+        self.tag_times.clear();
+        for record in history.get_records(&start, &SystemTime::now(), false) {
+            for (date, secs) in
+                self.calendar
+                    .split(record.start_time, record.end_time, record.duration)
+            {
+                if date < first {
+                    continue;
+                }
+                if date == self.today {
+                    self.total_time += secs;
+                }
+                if date >= week_start {
+                    self.week_time += secs;
+                }
+                for tag in record.tags.iter() {
+                    let times = self.tag_times.entry(tag.clone()).or_default();
+                    if date == self.today {
+                        times.day += secs;
+                    }
+                    if date >= week_start {
+                        times.week += secs;
+                    }
+                    if date >= month_start {
+                        times.month += secs;
+                    }
+                }
+            }
         }
     }
 
     fn ui(&mut self, ctx: &Context, ui: &mut Ui, setting: &Setting, history: &mut History) {
+        // Roll the working time over when a new day starts
+        let calendar = setting.calendar();
+        if calendar != self.calendar || calendar.today() != self.today {
+            self.calendar = calendar;
+            self.refresh_total_time(history);
+        }
+        let next_day = calendar.start_of(self.today + Days::new(1));
+        if let Ok(after) = next_day.duration_since(SystemTime::now()) {
+            ctx.request_repaint_after(after);
+        }
+
         let (is_timeout, counter_string) = self.timer.update();
+        // The next timer of a sequence may start in this frame.
+        let notify = self.timer.notify();
+        if is_timeout {
+            self.sequencer.timeout();
+        }
+        if self.timer.status() == Status::TimeOut {
+            self.sequence_ui(ctx, setting, history);
+        }
 
-        if self.timer.status() != Status::Stopped {
+        if matches!(self.timer.status(), Status::Started | Status::TimeOut) {
             ctx.request_repaint_after_secs(0.2);
         }
 
-        if is_timeout && self.timer.notify() {
+        if is_timeout && notify {
             ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
             ctx.send_viewport_cmd(ViewportCommand::WindowLevel(WindowLevel::AlwaysOnTop));
             self.on_top = true;
@@ -277,15 +575,77 @@ impl MainPanel {
                     ui.separator();
                     self.timer_buttons_ui(ui, setting, history);
                     ui.add_space(6.0);
-                    self.tags_ui(ui, setting.tags());
-                    self.timer_panel.ui(ui, self.timer.status(), counter_string);
+                    self.session_changed |= self.tags_ui(ui, setting);
+                    self.goal_ui(ui, setting);
+                    let warning = self.budget_warning(setting);
+                    self.timer_panel.ui(
+                        ui,
+                        self.timer.status(),
+                        counter_string,
+                        self.sequencer.round(),
+                        warning,
+                    );
                 },
             );
         });
+
+        self.heartbeat_session(ctx, setting, history);
+    }
+
+    fn heartbeat_session(&mut self, ctx: &Context, setting: &Setting, history: &mut History) {
+        const HEARTBEAT: Duration = Duration::from_secs(30);
+        if self.timer.status() == Status::Stopped || !self.timer.for_work() {
+            return;
+        }
+        if self.session_changed || self.heartbeat.elapsed() >= HEARTBEAT {
+            self.session_changed = false;
+            self.heartbeat = Instant::now();
+            if let Some(name) = self.timer.current_name() {
+                let saved = history.save_session(&Session {
+                    timer: name.to_string(),
+                    start_time: *self.timer.get_start_time(),
+                    tags: self.tags(setting).iter().map(|t| t.name.clone()).collect(),
+                    active_secs: self.timer.active_secs(),
+                    pauses: self.timer.pause_count(),
+                    paused: self.timer.status() == Status::Paused,
+                    heartbeat: SystemTime::now(),
+                });
+                // It's saved again on the next heartbeat.
+                error_ui::check(ctx, saved, "Saving the session", None);
+            }
+        }
+    }
+
+    fn resume_session(&mut self, session: &Session, setting: &Setting) {
+        if let Some(t) = setting
+            .timer_list()
+            .iter()
+            .find(|t| t.name == session.timer)
+        {
+            self.timer_panel
+                .set_info(format!("{} {}", &t.icon, &t.name), t.limit_time);
+            self.timer
+                .restore(t, session.start_time, session.active_secs, session.pauses);
+            let tag_ids: Vec<u64> = session
+                .tags
+                .iter()
+                .filter_map(|name| setting.tag(name))
+                .map(|t| t.id)
+                .collect();
+            if !tag_ids.is_empty() {
+                self.tag_ids = tag_ids;
+            }
+            if !session.paused {
+                self.toggle_pause(setting.audio_file());
+            }
+            self.session_changed = true;
+        }
     }
 
     fn timer_buttons_ui(&mut self, ui: &mut Ui, setting: &Setting, history: &mut History) {
-        let n = setting.timer_list().len();
+        let running = self.timer.status() != Status::Stopped;
+        let timer_n = setting.timer_list().len();
+        let n = timer_n + setting.sequences().len() + running as usize;
         ui.add_space(25.0);
         ui.horizontal(|ui| {
             ui.columns(n, |columns| {
@@ -300,8 +660,9 @@ impl MainPanel {
                         let btn = Button::new(&text).min_size(vec2(40.0, 40.0));
                         if ui.add(btn).clicked() {
                             self.audio.cancel_notify();
+                            self.sequencer.stop();
                             if self.timer.status() != Status::Stopped {
-                                self.stop(&setting.tags()[self.tag_index], history);
+                                self.stop(ui.ctx(), &self.tag_names(setting), history);
                             }
                             if !the_same {
                                 self.start(text, t, setting.audio_file());
@@ -309,50 +670,278 @@ impl MainPanel {
                         }
                     });
                 }
+                for (i, s) in setting.sequences().iter().enumerate() {
+                    columns[timer_n + i].vertical_centered_justified(|ui| {
+                        let the_same = self.sequencer.current_name() == Some(&s.name);
+                        let text = if the_same {
+                            "\u{23F9} Stop".to_string()
+                        } else {
+                            format!("{} {}", &s.icon, &s.name)
+                        };
+                        let btn = Button::new(&text).min_size(vec2(40.0, 40.0));
+                        if ui.add(btn).clicked() {
+                            self.audio.cancel_notify();
+                            self.sequencer.stop();
+                            if self.timer.status() != Status::Stopped {
+                                self.stop(ui.ctx(), &self.tag_names(setting), history);
+                            }
+                            if !the_same {
+                                self.start_sequence(ui.ctx(), s, setting);
+                            }
+                        }
+                    });
+                }
+                if running {
+                    columns[n - 1].vertical_centered_justified(|ui| {
+                        let text = if self.timer.status() == Status::Paused {
+                            "\u{25B6} Resume"
+                        } else {
+                            "\u{23F8} Pause"
+                        };
+                        let btn = Button::new(text).min_size(vec2(40.0, 40.0));
+                        if ui.add(btn).clicked() {
+                            self.toggle_pause(setting.audio_file());
+                        }
+                    });
+                }
             });
         });
     }
 
-    fn tags_ui(&mut self, ui: &mut Ui, tags: &[String]) {
-        let tag = ComboBox::from_id_salt("tag")
-            .width(ui.available_width())
-            .show_index(ui, &mut self.tag_index, tags.len(), |i| tags[i].to_string());
-        tag.on_hover_text("Tag. It's saved in the history when you stop the timer.");
+    fn tags<'a>(&self, setting: &'a Setting) -> Vec<&'a TagSetting> {
+        self.tag_ids
+            .iter()
+            .filter_map(|id| setting.tag_by_id(*id))
+            .collect()
+    }
+
+    /// The tags saved in the record, `UNTAGGED` if none is selected.
+    fn tag_names(&self, setting: &Setting) -> Vec<String> {
+        let tags: Vec<String> = self.tags(setting).iter().map(|t| t.name.clone()).collect();
+        if tags.is_empty() {
+            vec![UNTAGGED.to_string()]
+        } else {
+            tags
+        }
+    }
+
+    /// Several tags can be selected. Returns true if the selection is changed.
+    fn tags_ui(&mut self, ui: &mut Ui, setting: &Setting) -> bool {
+        let width = ui.available_width();
+        let InnerResponse { inner, response } =
+            tags_ui::tags_select_ui(ui, "tag", width, UNTAGGED, setting, |tag| {
+                self.tag_ids.contains(&tag.id)
+            });
+        response.on_hover_text("Tags. They are saved in the history when you stop the timer.");
+        let Some(tag) = inner else {
+            return false;
+        };
+        match self.tag_ids.iter().position(|id| *id == tag.id) {
+            Some(i) => {
+                self.tag_ids.remove(i);
+            }
+            None => self.tag_ids.push(tag.id),
+        }
+        true
     }
 
     fn start(&mut self, text: String, t: &TimerSetting, audio_file: Option<&str>) {
         self.timer_panel.set_info(text, t.limit_time);
         self.timer.start(t);
-        if t.notify
-            && let Some(audio_file) = audio_file
-        {
+        self.session_changed = true;
+        if t.notify {
+            self.schedule_notify(audio_file, t.limit_time * 60);
+        }
+    }
+
+    fn start_sequence(&mut self, ctx: &Context, s: &SequenceSetting, setting: &Setting) {
+        if let Some(name) = self.sequencer.start(s) {
+            self.start_by_name(ctx, &name, setting);
+        }
+    }
+
+    /// Starts a timer of the cycle, the cycle stops if the timer is deleted.
+    fn start_by_name(&mut self, ctx: &Context, name: &str, setting: &Setting) {
+        if let Some(t) = setting.timer_list().iter().find(|t| t.name == name) {
+            self.start(format!("{} {}", &t.icon, &t.name), t, setting.audio_file());
+        } else {
+            let cycle = self.sequencer.current_name().unwrap_or_default();
+            let error = Error::Invalid(format!("The timer \"{name}\" of \"{cycle}\" is not found"));
+            error_ui::report(ctx, "Starting the cycle", &error, None);
+            self.sequencer.stop();
+        }
+    }
+
+    fn sequence_ui(&mut self, ctx: &Context, setting: &Setting, history: &mut History) {
+        if let Some(advance) = self.sequencer.poll() {
+            self.stop(ctx, &self.tag_names(setting), history);
+            if let Advance::Next(name) = advance {
+                self.start_by_name(ctx, &name, setting);
+            }
+        }
+    }
+
+    fn toggle_pause(&mut self, audio_file: Option<&str>) {
+        if self.timer.status() == Status::Paused {
+            self.timer.resume();
+            if self.timer.notify()
+                && let Some(secs) = self.timer.remaining_secs()
+            {
+                self.schedule_notify(audio_file, secs);
+            }
+        } else {
+            self.audio.cancel_notify();
+            self.timer.pause();
+        }
+        self.session_changed = true;
+    }
+
+    fn schedule_notify(&mut self, audio_file: Option<&str>, after_secs: u64) {
+        if let Some(audio_file) = audio_file {
             let name = if audio_file.starts_with("assets/") {
                 self.app_path.join(audio_file)
             } else {
                 PathBuf::from(audio_file)
             };
-            self.audio.schedule_notify(name, t.limit_time * 60);
+            self.audio.schedule_notify(name, after_secs);
         }
     }
 
-    fn stop(&mut self, tag: &str, history: &mut History) {
-        if let Some((duration, _)) = self.timer.stop() {
-            self.total_time += duration;
-            history.add_record(self.timer.get_start_time(), duration, tag);
+    /// The session is cleared only after the record is saved, so it can still
+    /// be recovered if saving fails.
+    fn stop(&mut self, ctx: &Context, tags: &[String], history: &mut History) {
+        if let Some((duration, timer)) = self.timer.stop() {
+            let record = Record {
+                id: RecordId::default(),
+                start_time: *self.timer.get_start_time(),
+                end_time: SystemTime::now(),
+                duration,
+                tags: tags.to_vec(),
+                pauses: self.timer.pause_count(),
+                timer,
+                note: String::new(),
+            };
+            let added = history.add_record(&record);
+            if error_ui::check(
+                ctx,
+                added,
+                "Saving the record",
+                Some(Retry::AddRecord(record)),
+            )
+            .is_some()
+            {
+                error_ui::check(ctx, history.clear_session(), "Clearing the session", None);
+                self.refresh_total_time(history);
+            }
         }
     }
 
     fn total_string(&self) -> String {
-        let time = self.total_time;
-        const HOUR_SEC: u64 = 60 * 60;
-        if time >= HOUR_SEC {
-            format!(
-                "Working Time {} h {} m",
-                time / HOUR_SEC,
-                (time % HOUR_SEC) / 60
-            )
+        format!("Working Time {}", hm_string(self.total_time))
+    }
+
+    /// Active seconds of the running work timer, not in the history yet.
+    fn running_secs(&self) -> u64 {
+        if self.timer.status() != Status::Stopped && self.timer.for_work() {
+            self.timer.active_secs()
+        } else {
+            0
+        }
+    }
+
+    fn tag_times(&self, tag: &str) -> TagTimes {
+        self.tag_times.get(tag).copied().unwrap_or_default()
+    }
+
+    /// Warns if a selected tag is near or over its budget, the one with the
+    /// highest usage is reported.
+    fn budget_warning(&self, setting: &Setting) -> Option<(BudgetState, String)> {
+        let tags = self.tags(setting);
+        let usage = tags
+            .iter()
+            .filter_map(|tag| setting.budget(&tag.name))
+            .map(|budget| {
+                let times = self.tag_times(&budget.tag);
+                let used = match budget.period {
+                    BudgetPeriod::Week => times.week,
+                    BudgetPeriod::Month => times.month,
+                };
+                BudgetUsage {
+                    budget: budget.clone(),
+                    used: used + self.running_secs(),
+                }
+            })
+            .max_by(|a, b| a.ratio().total_cmp(&b.ratio()))?;
+        let period = usage.budget.period.name().to_lowercase();
+        let prefix = if tags.len() > 1 {
+            format!("{}: ", usage.budget.tag)
         } else {
-            format!("Working Time {} m", time / 60)
+            String::new()
+        };
+        match usage.state() {
+            BudgetState::Normal => None,
+            BudgetState::Near => Some((
+                BudgetState::Near,
+                format!(
+                    "{prefix}{} left in this {period}'s budget",
+                    hm_string(usage.remaining())
+                ),
+            )),
+            BudgetState::Over => Some((
+                BudgetState::Over,
+                format!(
+                    "{prefix}{} over this {period}'s budget",
+                    hm_string(usage.used - usage.limit())
+                ),
+            )),
+        }
+    }
+
+    /// Progress bars of the goals, the running work timer is included.
+    fn goal_ui(&self, ui: &mut Ui, setting: &Setting) {
+        let running = self.running_secs();
+        let goal = setting.goal();
+        let mut bars = vec![
+            ("Today".to_string(), self.total_time, goal.daily),
+            ("This week".to_string(), self.week_time, goal.weekly),
+        ];
+        for tag_goal in self
+            .tags(setting)
+            .iter()
+            .filter_map(|tag| goal.tag(&tag.name))
+        {
+            let times = self.tag_times(&tag_goal.tag);
+            bars.push((format!("{} today", tag_goal.tag), times.day, tag_goal.daily));
+            bars.push((
+                format!("{} this week", tag_goal.tag),
+                times.week,
+                tag_goal.weekly,
+            ));
+        }
+
+        // Bottom-up layout, the first bar is drawn at the bottom
+        for (name, done, goal) in bars.into_iter().rev() {
+            if goal == 0 {
+                continue;
+            }
+            let done = done + running;
+            let goal = goal * 60;
+            let mut bar = ProgressBar::new(done as f32 / goal as f32);
+            bar = if done >= goal {
+                bar.fill(MyColor::green(ui)).text(format!(
+                    "{name} {} / {} \u{2714}",
+                    hm_string(done),
+                    hm_string(goal)
+                ))
+            } else {
+                bar.text(format!(
+                    "{name} {} / {}, {} left",
+                    hm_string(done),
+                    hm_string(goal),
+                    hm_string(goal - done)
+                ))
+            };
+            ui.add(bar);
         }
     }
 }
@@ -383,6 +972,7 @@ impl TimerPanel {
         self.frame.fill = match self.status {
             Status::Stopped => Color32::TRANSPARENT,
             Status::Started => MyColor::green(ui),
+            Status::Paused => MyColor::yellow(ui),
             Status::TimeOut => MyColor::red(ui),
         };
     }
@@ -399,25 +989,44 @@ impl TimerPanel {
         }
     }
 
-    fn ui(&mut self, ui: &mut Ui, status: Status, counter_string: String) {
+    fn ui(
+        &mut self,
+        ui: &mut Ui,
+        status: Status,
+        counter_string: String,
+        round: Option<(u32, u32)>,
+        warning: Option<(BudgetState, String)>,
+    ) {
         self.update(ui, status);
         self.frame.show(ui, |ui| {
             ui.add_space(ui.available_height() / 2.0 - 68.0);
             ui.label(format!("Limit {} m", self.limit_time));
             ui.label(RichText::new(counter_string).font(FontId::proportional(80.0)));
             ui.label(&self.name);
+            if let Some((round, rounds)) = round {
+                ui.label(format!("Round {round}/{rounds}"));
+            }
+            if let Some((state, text)) = warning {
+                let color = if state == BudgetState::Over {
+                    ui.visuals().error_fg_color
+                } else {
+                    ui.visuals().warn_fg_color
+                };
+                ui.colored_label(color, format!("\u{26A0} {text}"));
+            }
             ui.add_space(ui.available_height());
         });
     }
 }
 
-pub fn get_time_from_offset_days(days: i64) -> SystemTime {
-    let date = Local::now().date_naive() + chrono::Duration::days(days);
-    let time = date.and_hms_opt(0, 0, 0).unwrap();
-    time.and_local_timezone(chrono::Local)
-        .single()
-        .unwrap()
-        .into()
+/// Formats seconds like "2 h 5 m".
+pub fn hm_string(secs: u64) -> String {
+    const HOUR_SEC: u64 = 60 * 60;
+    if secs >= HOUR_SEC {
+        format!("{} h {} m", secs / HOUR_SEC, (secs % HOUR_SEC) / 60)
+    } else {
+        format!("{} m", secs / 60)
+    }
 }
 
 // ----------------------------------------------------------------------------
@@ -443,6 +1052,14 @@ impl MyColor {
         }
     }
 
+    fn yellow(ui: &mut Ui) -> Color32 {
+        if ui.ctx().theme() == Theme::Dark {
+            Color32::from_rgb(105, 90, 35)
+        } else {
+            Color32::from_rgb(250, 220, 120)
+        }
+    }
+
     fn background(ui: &mut Ui) -> Color32 {
         if ui.ctx().theme() == Theme::Dark {
             Color32::from_rgb(40, 40, 40)
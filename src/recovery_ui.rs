@@ -0,0 +1,80 @@
+use chrono::{DateTime, Local};
+use eframe::egui::{Button, Id, Modal, Sides, Ui};
+
+use crate::{MyColor, history::Session};
+
+pub enum Recovery {
+    Resume(Session),
+    Save(Session),
+    Discard,
+}
+
+pub struct RecoveryWindow {
+    session: Option<Session>,
+}
+
+impl RecoveryWindow {
+    pub fn new(session: Option<Session>) -> Self {
+        Self { session }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, can_resume: bool) -> Option<Recovery> {
+        let session = self.session.as_ref()?;
+        let mut rst = None;
+
+        let modal = Modal::new(Id::new("recovery")).backdrop_color(MyColor::MODAL_BG);
+        modal.show(ui.ctx(), |ui| {
+            ui.set_width(350.0);
+            ui.heading("Recover the unfinished session?");
+            ui.add_space(10.0);
+
+            let start_time: DateTime<Local> = session.start_time.into();
+            let heartbeat: DateTime<Local> = session.heartbeat.into();
+            ui.label(format!(
+                "{} started at {}",
+                session.timer,
+                start_time.format("%Y-%m-%d %H:%M:%S")
+            ));
+            ui.label(format!(
+                "Working time {} until {}",
+                crate::timer::secs_to_string(session.active_secs, ""),
+                heartbeat.format("%H:%M:%S")
+            ));
+            ui.add_space(20.0);
+
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui
+                        .add_enabled(can_resume, Button::new("Resume"))
+                        .on_disabled_hover_text("The timer no longer exists.")
+                        .clicked()
+                    {
+                        rst = Some(Recovery::Resume(session.clone()));
+                    }
+                    if ui.button("Save record").clicked() {
+                        rst = Some(Recovery::Save(session.clone()));
+                    }
+                    if ui.button("Discard").clicked() {
+                        rst = Some(Recovery::Discard);
+                    }
+                },
+            );
+        });
+
+        if rst.is_some() {
+            self.session = None;
+        }
+        rst
+    }
+
+    /// True until the session is resumed, saved or discarded.
+    pub fn is_pending(&self) -> bool {
+        self.session.is_some()
+    }
+
+    pub fn timer(&self) -> Option<&str> {
+        self.session.as_ref().map(|s| s.timer.as_str())
+    }
+}
@@ -0,0 +1,87 @@
+use crate::setting::SequenceSetting;
+use std::time::{Duration, Instant};
+
+pub enum Advance {
+    Next(String),
+    Finished,
+}
+
+pub struct Sequencer {
+    setting: Option<SequenceSetting>,
+    round: u32,
+    step: usize,
+    next_instant: Option<Instant>,
+}
+
+impl Sequencer {
+    pub fn new() -> Self {
+        Self {
+            setting: None,
+            round: 1,
+            step: 0,
+            next_instant: None,
+        }
+    }
+
+    /// Returns the name of the first timer, or `None` if the sequence is empty.
+    pub fn start(&mut self, setting: &SequenceSetting) -> Option<String> {
+        if setting.timers.is_empty() {
+            return None;
+        }
+        self.setting = Some(setting.clone());
+        self.round = 1;
+        self.step = 0;
+        self.next_instant = None;
+        self.current_timer()
+    }
+
+    pub fn stop(&mut self) {
+        self.setting = None;
+        self.next_instant = None;
+    }
+
+    pub fn current_name(&self) -> Option<&str> {
+        self.setting.as_ref().map(|s| s.name.as_str())
+    }
+
+    pub fn round(&self) -> Option<(u32, u32)> {
+        self.setting.as_ref().map(|s| (self.round, s.rounds.max(1)))
+    }
+
+    pub fn timeout(&mut self) {
+        if let Some(s) = self.setting.as_ref()
+            && self.next_instant.is_none()
+        {
+            self.next_instant = Some(Instant::now() + Duration::from_secs(s.grace_secs));
+        }
+    }
+
+    /// Moves to the next step once the grace period after a timeout is over.
+    pub fn poll(&mut self) -> Option<Advance> {
+        if self.next_instant? > Instant::now() {
+            return None;
+        }
+        self.next_instant = None;
+
+        let s = self.setting.as_ref()?;
+        self.step += 1;
+        if self.step >= s.timers.len() {
+            self.step = 0;
+            self.round += 1;
+        }
+        if self.round > s.rounds.max(1) {
+            self.stop();
+            return Some(Advance::Finished);
+        }
+        self.current_timer().map(Advance::Next)
+    }
+
+    fn current_timer(&self) -> Option<String> {
+        let s = self.setting.as_ref()?;
+        let is_last = self.round >= s.rounds.max(1) && self.step + 1 == s.timers.len();
+        if is_last && let Some(long_break) = s.long_break.as_ref() {
+            return Some(long_break.clone());
+        }
+        s.timers.get(self.step).cloned()
+    }
+}
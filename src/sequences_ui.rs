@@ -0,0 +1,233 @@
+use eframe::egui::{self, ComboBox, Frame, Id, Modal, Sides, Ui, vec2};
+
+use crate::{
+    MyColor,
+    error_ui::{self, Retry},
+    setting::{SequenceSetting, Setting},
+};
+
+pub struct SequencesWindow {
+    show: bool,
+    delete_index: Option<usize>,
+    sequence_info_list: Vec<SequenceInfo>,
+}
+
+impl SequencesWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            delete_index: None,
+            sequence_info_list: Vec::new(),
+        }
+    }
+
+    pub fn show(&mut self, setting: &Setting) {
+        self.refresh_info(setting);
+        self.show = true;
+    }
+
+    fn refresh_info(&mut self, setting: &Setting) {
+        self.sequence_info_list.clear();
+        for sequence in setting.sequences() {
+            self.sequence_info_list
+                .push(SequenceInfo::from_setting(sequence));
+        }
+    }
+
+    pub fn ui(&mut self, ui: &mut Ui, setting: &mut Setting) {
+        if self.show {
+            let timer_names: Vec<String> = setting
+                .timer_list()
+                .iter()
+                .map(|t| t.name.clone())
+                .collect();
+
+            let modal = Modal::new(Id::new("sequences")).backdrop_color(MyColor::MODAL_BG);
+            let response = modal.show(ui.ctx(), |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Cycles");
+                    if ui.button("New").clicked() {
+                        setting.add_sequence(SequenceSetting::new());
+                        self.refresh_info(setting);
+                    }
+                });
+                ui.separator();
+
+                ui.set_min_size(vec2(200.0, 64.0));
+                for (i, sequence) in setting.mut_sequences().iter_mut().enumerate() {
+                    self.item_ui(ui, i, sequence, &timer_names);
+                }
+            });
+
+            if response.should_close() {
+                error_ui::check(
+                    ui.ctx(),
+                    setting.save(),
+                    "Saving the setting",
+                    Some(Retry::SaveSetting),
+                );
+                self.show = false;
+            }
+        }
+
+        self.delete_sequence_window_ui(ui, setting);
+    }
+
+    fn item_ui(
+        &mut self,
+        ui: &mut Ui,
+        i: usize,
+        sequence: &mut SequenceSetting,
+        timer_names: &[String],
+    ) {
+        let sequence_info = &mut self.sequence_info_list[i];
+        Frame::default()
+            .fill(MyColor::background(ui))
+            .inner_margin(4.0)
+            .show(ui, |ui| {
+                ui.vertical(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_sized([15.0, 20.0], egui::TextEdit::singleline(&mut sequence.icon));
+                        if ui
+                            .add_sized(
+                                [240.0, 20.0],
+                                egui::TextEdit::singleline(&mut sequence.name),
+                            )
+                            .lost_focus()
+                        {
+                            if sequence.name.is_empty() {
+                                sequence.name = sequence_info.name.clone();
+                            } else {
+                                sequence_info.name = sequence.name.clone();
+                            }
+                        }
+                        if ui.button("Delete").clicked() {
+                            self.delete_index = Some(i);
+                        }
+                    });
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Timers:");
+                        let mut remove = None;
+                        for (j, timer) in sequence.timers.iter_mut().enumerate() {
+                            ComboBox::from_id_salt(("sequence_timer", i, j))
+                                .selected_text(timer.as_str())
+                                .width(80.0)
+                                .show_ui(ui, |ui| {
+                                    for name in timer_names {
+                                        ui.selectable_value(timer, name.clone(), name);
+                                    }
+                                });
+                            if ui.small_button("\u{2716}").clicked() {
+                                remove = Some(j);
+                            }
+                        }
+                        if let Some(j) = remove {
+                            sequence.timers.remove(j);
+                        }
+                        if let Some(first) = timer_names.first()
+                            && ui
+                                .button("+")
+                                .on_hover_text("Add a timer to the round")
+                                .clicked()
+                        {
+                            sequence.timers.push(first.clone());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Rounds:");
+                        if ui
+                            .add_sized(
+                                [60.0, 20.0],
+                                egui::TextEdit::singleline(&mut sequence_info.rounds),
+                            )
+                            .lost_focus()
+                        {
+                            if let Ok(value) = sequence_info.rounds.parse::<u32>() {
+                                sequence.rounds = value.max(1);
+                            }
+                            sequence_info.rounds = sequence.rounds.to_string();
+                        }
+                        ui.label("Grace period:");
+                        if ui
+                            .add_sized(
+                                [60.0, 20.0],
+                                egui::TextEdit::singleline(&mut sequence_info.grace_secs),
+                            )
+                            .lost_focus()
+                        {
+                            if let Ok(value) = sequence_info.grace_secs.parse::<u64>() {
+                                sequence.grace_secs = value;
+                            }
+                            sequence_info.grace_secs = sequence.grace_secs.to_string();
+                        }
+                        ui.label("seconds");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Long break:");
+                        ComboBox::from_id_salt(("sequence_long_break", i))
+                            .selected_text(sequence.long_break.as_deref().unwrap_or("None"))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(&mut sequence.long_break, None, "None");
+                                for name in timer_names {
+                                    ui.selectable_value(
+                                        &mut sequence.long_break,
+                                        Some(name.clone()),
+                                        name,
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text("Replaces the last timer of the final round.");
+                    });
+                });
+            });
+    }
+
+    fn delete_sequence_window_ui(&mut self, ui: &mut Ui, setting: &mut Setting) {
+        if let Some(index) = self.delete_index {
+            let modal = Modal::new(Id::new("sequence_delete")).backdrop_color(MyColor::MODAL_BG);
+            let response = modal.show(ui.ctx(), |ui| {
+                ui.set_width(350.0);
+                ui.heading("Are you sure you want to delete this cycle?");
+                let sequence = &setting.sequences()[index];
+                ui.add_space(10.0);
+                ui.label(format!("{} {}", sequence.icon, sequence.name));
+                ui.add_space(20.0);
+                Sides::new().show(
+                    ui,
+                    |_ui| {},
+                    |ui| {
+                        if ui.button("Yes").clicked() {
+                            setting.mut_sequences().remove(index);
+                            self.refresh_info(setting);
+                            self.delete_index = None;
+                        }
+
+                        if ui.button("No").clicked() {
+                            self.delete_index = None;
+                        }
+                    },
+                );
+            });
+            if response.should_close() {
+                self.delete_index = None;
+            }
+        }
+    }
+}
+
+struct SequenceInfo {
+    name: String,
+    rounds: String,
+    grace_secs: String,
+}
+
+impl SequenceInfo {
+    fn from_setting(setting: &SequenceSetting) -> Self {
+        Self {
+            name: setting.name.clone(),
+            rounds: setting.rounds.to_string(),
+            grace_secs: setting.grace_secs.to_string(),
+        }
+    }
+}
@@ -1,7 +1,10 @@
-use eframe::egui::ThemePreference;
-use serde::{Deserialize, Serialize};
+use crate::{calendar::Calendar, error::Error};
+use chrono::{NaiveTime, Weekday};
+use eframe::egui::{Color32, ThemePreference, ecolor::Hsva};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{
     fs,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
 };
 
@@ -10,101 +13,126 @@ pub struct Setting {
     cache_info: CacheInfo,
     file_name: PathBuf,
     info: SettingInfo,
+    /// Why the config file couldn't be loaded, reported once in the UI.
+    load_error: Option<Error>,
 }
 
 impl Setting {
-    pub fn new() -> Self {
-        let mut file_name = get_config_dir();
+    pub fn new() -> Result<Self, Error> {
+        let mut file_name = get_config_dir()?;
 
         let mut cache_name = file_name.clone();
         cache_name.push("cache.toml");
 
         file_name.push("config.toml");
 
-        let info = Self::load_setting(&file_name);
+        let (info, load_error) = Self::load_setting(&file_name)?;
         let mut cache_info = Self::load_cache(&cache_name);
-        if cache_info.tag_index >= info.tags.len() {
-            cache_info.tag_index = 0;
+        cache_info
+            .tag_ids
+            .retain(|id| info.tags.iter().any(|t| t.id == *id && !t.archived));
+        if cache_info.tag_ids.is_empty() {
+            cache_info.tag_ids = info
+                .tags
+                .iter()
+                .find(|t| !t.archived)
+                .map(|t| t.id)
+                .into_iter()
+                .collect();
         }
 
-        Self {
+        Ok(Self {
             cache_name,
             cache_info,
             file_name,
             info,
-        }
+            load_error,
+        })
     }
 
-    fn load_setting(file_name: &Path) -> SettingInfo {
-        let mut need_save = true;
+    /// A broken file is copied to "config.toml.bak" and returned as error
+    /// with the default setting, it's not overwritten until the setting is saved.
+    fn load_setting(file_name: &Path) -> Result<(SettingInfo, Option<Error>), Error> {
+        if !file_name.exists() {
+            let info = SettingInfo::default();
+            fs::write(file_name, toml::to_string(&info)?)?;
+            return Ok((info, None));
+        }
 
-        let mut info = SettingInfo {
-            theme: Theme::System,
-            audio_file: "assets/notify.wav".to_string(),
-            play_audio: true,
-            tags: vec![
-                "Program".to_string(),
-                "English".to_string(),
-                "Read".to_string(),
-            ],
-            timer_list: vec![
-                TimerSetting {
-                    name: "Break".to_string(),
-                    icon: "\u{2615}".to_string(),
-                    limit_time: 5,
-                    for_work: false,
-                    count_up: false,
-                    notify: true,
-                },
-                TimerSetting {
-                    name: "Work".to_string(),
-                    icon: "\u{1F4BB}".to_string(),
-                    limit_time: 25,
-                    for_work: true,
-                    count_up: true,
-                    notify: false,
-                },
-            ],
-        };
-
-        // Load
-        if file_name.exists() {
-            let toml_str = fs::read_to_string(file_name).unwrap();
-            if let Ok(i) = toml::from_str(&toml_str) {
-                info = i;
-                need_save = false;
+        let toml_str = fs::read_to_string(file_name)?;
+        match toml::from_str::<SettingInfo>(&toml_str) {
+            Ok(mut info) => {
+                if info.migrate() {
+                    fs::write(file_name, toml::to_string(&info)?)?;
+                }
+                Ok((info, None))
+            }
+            Err(e) => {
+                let backup = file_name.with_extension("toml.bak");
+                fs::copy(file_name, &backup)?;
+                let error = Error::broken_config(&toml_str, &e, backup);
+                Ok((SettingInfo::default(), Some(error)))
             }
         }
+    }
+
+    /// The error of loading the config file, `None` after the first call.
+    pub fn take_load_error(&mut self) -> Option<Error> {
+        self.load_error.take()
+    }
 
-        // Save
-        if need_save {
-            fs::write(file_name, toml::to_string(&info).unwrap()).unwrap();
+    /// Tags saved before they had ids. Returns true if any is assigned.
+    fn assign_tag_ids(info: &mut SettingInfo) -> bool {
+        let mut assigned = false;
+        while let Some(i) = info.tags.iter().position(|t| t.id == 0) {
+            info.tags[i].id = info.take_tag_id();
+            assigned = true;
         }
-        info
+        assigned
+    }
+
+    /// The content of the config file.
+    pub fn to_toml(&self) -> Result<String, Error> {
+        Ok(toml::to_string(&self.info)?)
+    }
+
+    /// Checks that `text` is a valid config file.
+    pub fn validate(text: &str) -> Result<(), Error> {
+        toml::from_str::<SettingInfo>(text)?;
+        Ok(())
+    }
+
+    /// Replaces the setting with the content of a config file and saves it.
+    pub fn restore(&mut self, text: &str) -> Result<(), Error> {
+        let mut info: SettingInfo = toml::from_str(text)?;
+        info.migrate();
+        self.cache_info
+            .tag_ids
+            .retain(|id| info.tags.iter().any(|t| t.id == *id && !t.archived));
+        self.info = info;
+        self.save()
     }
 
     fn load_cache(file_name: &Path) -> CacheInfo {
-        let mut info = CacheInfo {
-            maximized: false,
-            window: None,
-            tag_index: 0,
-        };
-
-        if file_name.exists() {
-            let toml_str = fs::read_to_string(file_name).unwrap();
-            if let Ok(i) = toml::from_str(&toml_str) {
-                info = i;
-            }
+        let mut info = CacheInfo::default();
+
+        // The cache is only a convenience, so an unreadable one is ignored.
+        if let Ok(toml_str) = fs::read_to_string(file_name)
+            && let Ok(i) = toml::from_str(&toml_str)
+        {
+            info = i;
         }
         info
     }
 
-    pub fn save(&self) {
-        fs::write(&self.file_name, toml::to_string(&self.info).unwrap()).unwrap();
+    pub fn save(&self) -> Result<(), Error> {
+        fs::write(&self.file_name, toml::to_string(&self.info)?)?;
+        Ok(())
     }
 
-    pub fn save_cache(&self) {
-        fs::write(&self.cache_name, toml::to_string(&self.cache_info).unwrap()).unwrap();
+    pub fn save_cache(&self) -> Result<(), Error> {
+        fs::write(&self.cache_name, toml::to_string(&self.cache_info)?)?;
+        Ok(())
     }
 
     pub fn window_info(&self) -> Option<&WindowInfo> {
@@ -123,14 +151,35 @@ impl Setting {
         self.cache_info.maximized = maximized;
     }
 
-    pub fn tags(&self) -> &[String] {
+    pub fn tags(&self) -> &[TagSetting] {
         self.info.tags.as_slice()
     }
 
-    pub fn mut_tags(&mut self) -> &mut Vec<String> {
+    pub fn mut_tags(&mut self) -> &mut Vec<TagSetting> {
         &mut self.info.tags
     }
 
+    pub fn tag(&self, name: &str) -> Option<&TagSetting> {
+        self.info.tags.iter().find(|t| t.name == name)
+    }
+
+    /// The color of a tag, or of the first child of a parent.
+    pub fn tag_color(&self, name: &str) -> Option<Color32> {
+        self.tag(name)
+            .or_else(|| self.info.tags.iter().find(|t| t.parent() == name))
+            .map(|t| t.color())
+    }
+
+    pub fn tag_by_id(&self, id: u64) -> Option<&TagSetting> {
+        self.info.tags.iter().find(|t| t.id == id)
+    }
+
+    /// A tag with an id never used before.
+    pub fn new_tag(&mut self, name: String) -> TagSetting {
+        let id = self.info.take_tag_id();
+        TagSetting::new(id, name)
+    }
+
     pub fn theme(&self) -> Theme {
         self.info.theme
     }
@@ -151,6 +200,107 @@ impl Setting {
         &mut self.info.timer_list
     }
 
+    /// The cycles refer to timers by name, they follow the renamed timer.
+    pub fn rename_timer(&mut self, old: &str, new: &str) {
+        for sequence in self.info.sequences.iter_mut() {
+            for name in sequence
+                .timers
+                .iter_mut()
+                .chain(sequence.long_break.iter_mut())
+            {
+                if name == old {
+                    *name = new.to_string();
+                }
+            }
+        }
+    }
+
+    pub fn sequences(&self) -> &[SequenceSetting] {
+        &self.info.sequences
+    }
+
+    pub fn add_sequence(&mut self, sequence: SequenceSetting) {
+        self.info.sequences.push(sequence);
+    }
+
+    pub fn mut_sequences(&mut self) -> &mut Vec<SequenceSetting> {
+        &mut self.info.sequences
+    }
+
+    pub fn calendar(&self) -> Calendar {
+        Calendar::new(self.info.day_start, self.info.week_start)
+    }
+
+    pub fn day_start(&self) -> NaiveTime {
+        self.info.day_start
+    }
+
+    pub fn set_day_start(&mut self, v: NaiveTime) {
+        self.info.day_start = v;
+    }
+
+    pub fn week_start(&self) -> Weekday {
+        self.info.week_start
+    }
+
+    pub fn set_week_start(&mut self, v: Weekday) {
+        self.info.week_start = v;
+    }
+
+    pub fn trash_days(&self) -> u64 {
+        self.info.trash_days
+    }
+
+    pub fn set_trash_days(&mut self, v: u64) {
+        self.info.trash_days = v;
+    }
+
+    pub fn backup(&self) -> &BackupSetting {
+        &self.info.backup
+    }
+
+    pub fn mut_backup(&mut self) -> &mut BackupSetting {
+        &mut self.info.backup
+    }
+
+    /// The configured directory, or "backups" in the config directory.
+    pub fn backup_dir(&self) -> PathBuf {
+        if self.info.backup.dir.is_empty() {
+            // The config file is in the config directory.
+            self.file_name.with_file_name("backups")
+        } else {
+            PathBuf::from(&self.info.backup.dir)
+        }
+    }
+
+    pub fn goal(&self) -> &GoalSetting {
+        &self.info.goal
+    }
+
+    pub fn mut_goal(&mut self) -> &mut GoalSetting {
+        &mut self.info.goal
+    }
+
+    pub fn budgets(&self) -> &[TagBudget] {
+        self.info.budgets.as_slice()
+    }
+
+    pub fn budget(&self, tag: &str) -> Option<&TagBudget> {
+        self.info.budgets.iter().find(|b| b.tag == tag)
+    }
+
+    /// Replaces the budget of `tag`, a budget of 0 hours is removed.
+    pub fn set_budget(&mut self, budget: TagBudget) {
+        self.remove_budget(&budget.tag);
+        if budget.hours > 0 {
+            self.info.budgets.push(budget);
+        }
+    }
+
+    pub fn remove_budget(&mut self, tag: &str) {
+        self.info.budgets.retain(|b| b.tag != tag);
+    }
+
     pub fn audio_file(&self) -> Option<&str> {
         if self.info.play_audio {
             Some(&self.info.audio_file)
@@ -171,35 +321,36 @@ impl Setting {
         self.info.play_audio
     }
 
-    pub fn set_tag_index(&mut self, v: usize) {
-        self.cache_info.tag_index = v;
+    pub fn set_tag_ids(&mut self, v: Vec<u64>) {
+        self.cache_info.tag_ids = v;
     }
 
-    pub fn tag_index(&self) -> usize {
-        self.cache_info.tag_index
+    pub fn tag_ids(&self) -> &[u64] {
+        &self.cache_info.tag_ids
     }
 }
 
-pub fn get_config_dir() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap();
+pub fn get_config_dir() -> Result<PathBuf, Error> {
+    let mut path = dirs::config_dir().ok_or(Error::NoConfigDir)?;
     #[cfg(debug_assertions)]
     path.push("work_timer_egui_dbg");
     #[cfg(not(debug_assertions))]
     path.push("work_timer_egui");
 
     if !path.exists() {
-        fs::create_dir_all(&path).unwrap();
+        fs::create_dir_all(&path)?;
     }
-    path
+    Ok(path)
 }
 
 // ----------------------------------------------------------------------------
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Default)]
+#[serde(default)]
 struct CacheInfo {
     maximized: bool,
     window: Option<WindowInfo>,
-    tag_index: usize,
+    tag_ids: Vec<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -212,13 +363,202 @@ pub struct WindowInfo {
 
 // ----------------------------------------------------------------------------
 
+/// A missing field takes its default, so an older or a hand edited file
+/// still loads.
 #[derive(Deserialize, Serialize)]
+#[serde(default)]
 struct SettingInfo {
+    /// `SettingInfo::VERSION` when saved, files before it have none.
+    #[serde(default)]
+    version: u32,
     theme: Theme,
     play_audio: bool,
     audio_file: String,
-    tags: Vec<String>,
+    #[serde(deserialize_with = "deserialize_tags")]
+    tags: Vec<TagSetting>,
+    /// Ids of deleted tags aren't reused, since the selection and the cache
+    /// refer to tags by id.
+    #[serde(default)]
+    next_tag_id: u64,
     timer_list: Vec<TimerSetting>,
+    sequences: Vec<SequenceSetting>,
+    /// Work before this time belongs to the previous day.
+    day_start: NaiveTime,
+    week_start: Weekday,
+    goal: GoalSetting,
+    budgets: Vec<TagBudget>,
+    /// Days to keep deleted records in the trash.
+    #[serde(deserialize_with = "deserialize_trash_days")]
+    trash_days: u64,
+    backup: BackupSetting,
+}
+
+impl SettingInfo {
+    /// 1: tags have ids.
+    /// 2: the next tag id is kept.
+    const VERSION: u32 = 2;
+
+    /// Upgrades a file of an older version, and gives ids to the tags added
+    /// by hand without one. Returns true if it's changed.
+    fn migrate(&mut self) -> bool {
+        let mut changed = self.version < Self::VERSION;
+        if self.version < 2 {
+            // Ids of tags deleted before are unknown.
+            self.next_tag_id = self.tags.iter().map(|t| t.id + 1).max().unwrap_or(1);
+        }
+        // No tag has an id before version 1.
+        changed |= Setting::assign_tag_ids(self);
+        self.version = Self::VERSION;
+        changed
+    }
+
+    fn take_tag_id(&mut self) -> u64 {
+        // Tags added to the file by hand may be above the kept id.
+        let id = self
+            .tags
+            .iter()
+            .map(|t| t.id + 1)
+            .max()
+            .unwrap_or(1)
+            .max(self.next_tag_id);
+        self.next_tag_id = id + 1;
+        id
+    }
+}
+
+impl Default for SettingInfo {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            theme: Theme::System,
+            audio_file: "assets/notify.wav".to_string(),
+            play_audio: true,
+            tags: vec![
+                TagSetting::new(1, "Program".to_string()),
+                TagSetting::new(2, "English".to_string()),
+                TagSetting::new(3, "Read".to_string()),
+            ],
+            next_tag_id: 4,
+            timer_list: vec![
+                TimerSetting {
+                    name: "Break".to_string(),
+                    icon: "\u{2615}".to_string(),
+                    limit_time: 5,
+                    for_work: false,
+                    count_up: false,
+                    notify: true,
+                },
+                TimerSetting {
+                    name: "Work".to_string(),
+                    icon: "\u{1F4BB}".to_string(),
+                    limit_time: 25,
+                    for_work: true,
+                    count_up: true,
+                    notify: false,
+                },
+            ],
+            sequences: vec![SequenceSetting {
+                name: "Pomodoro".to_string(),
+                icon: "\u{1F345}".to_string(),
+                timers: vec!["Work".to_string(), "Break".to_string()],
+                rounds: 4,
+                long_break: None,
+                grace_secs: 0,
+            }],
+            day_start: NaiveTime::MIN,
+            week_start: Weekday::Mon,
+            goal: GoalSetting::default(),
+            budgets: Vec::new(),
+            trash_days: 30,
+            backup: BackupSetting::default(),
+        }
+    }
+}
+
+pub const TRASH_DAYS: RangeInclusive<u64> = 1..=365;
+
+/// A hand edited value out of `TRASH_DAYS` is clamped.
+fn deserialize_trash_days<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let days = u64::deserialize(deserializer)?;
+    Ok(days.clamp(*TRASH_DAYS.start(), *TRASH_DAYS.end()))
+}
+
+/// Tags used to be plain names, they get a color by their position. A hand
+/// edited tag may miss any field but the name.
+fn deserialize_tags<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TagSetting>, D::Error> {
+    #[derive(Deserialize)]
+    struct TagFields {
+        #[serde(default)]
+        id: u64,
+        #[serde(default)]
+        name: String,
+        #[serde(default)]
+        color: Option<[u8; 3]>,
+        #[serde(default)]
+        archived: bool,
+        #[serde(default)]
+        description: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum TagEntry {
+        Name(String),
+        Tag(TagFields),
+    }
+
+    let entries = Vec::<TagEntry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, entry)| match entry {
+            TagEntry::Name(name) => Some(TagSetting::new(i as u64 + 1, name)),
+            // A tag without a name can't be used, it's dropped.
+            TagEntry::Tag(fields) if fields.name.trim().is_empty() => None,
+            TagEntry::Tag(fields) => {
+                // A tag without an id gets one on migration, till then the
+                // position picks the color.
+                let index = if fields.id > 0 {
+                    fields.id
+                } else {
+                    i as u64 + 1
+                };
+                let mut tag = TagSetting::new(index, fields.name);
+                tag.id = fields.id;
+                if let Some(color) = fields.color {
+                    tag.color = color;
+                }
+                tag.archived = fields.archived;
+                tag.description = fields.description;
+                Some(tag)
+            }
+        })
+        .collect())
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct BackupSetting {
+    /// Empty for the default directory.
+    #[serde(default)]
+    pub dir: String,
+    /// The number of backups to keep.
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+}
+
+impl Default for BackupSetting {
+    fn default() -> Self {
+        Self {
+            dir: String::new(),
+            keep: default_backup_keep(),
+        }
+    }
+}
+
+fn default_backup_keep() -> usize {
+    7
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy)]
@@ -248,7 +588,151 @@ impl From<Theme> for ThemePreference {
     }
 }
 
+/// The tag of records saved without any tag selected.
+pub const UNTAGGED: &str = "Untagged";
+
+/// Tags like "ClientA/Backend" are grouped under the parent "ClientA".
+pub const TAG_SEPARATOR: char = '/';
+
+pub fn tag_parent(tag: &str) -> &str {
+    tag.split_once(TAG_SEPARATOR)
+        .map_or(tag, |(parent, _)| parent)
+}
+
+/// Trims the spaces around the separator, e.g. "ClientA / Backend".
+pub fn normalize_tag(tag: &str) -> String {
+    match tag.split_once(TAG_SEPARATOR) {
+        Some((parent, child)) if !child.trim().is_empty() => {
+            format!("{}{TAG_SEPARATOR}{}", parent.trim(), child.trim())
+        }
+        Some((parent, _)) => parent.trim().to_string(),
+        None => tag.trim().to_string(),
+    }
+}
+
+/// Groups the tags by parent in the order they first appear.
+pub fn tag_groups<'a>(
+    tags: impl Iterator<Item = &'a TagSetting>,
+) -> Vec<(&'a str, Vec<&'a TagSetting>)> {
+    let mut groups: Vec<(&str, Vec<&TagSetting>)> = Vec::new();
+    for tag in tags {
+        match groups
+            .iter_mut()
+            .find(|(parent, _)| *parent == tag.parent())
+        {
+            Some((_, children)) => children.push(tag),
+            None => groups.push((tag.parent(), vec![tag])),
+        }
+    }
+    groups
+}
+
+/// Parents having children among `tags`, sorted.
+pub fn tag_parents<'a>(tags: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut parents: Vec<String> = tags
+        .filter(|tag| tag_parent(tag) != tag.as_str())
+        .map(|tag| tag_parent(tag).to_string())
+        .collect();
+    parents.sort();
+    parents.dedup();
+    parents
+}
+
+/// How records are grouped by tag in charts and reports.
+#[derive(Clone, PartialEq)]
+pub enum TagLevel {
+    /// A record with several tags counts fully for each of them.
+    Tag,
+    Parent,
+    /// Only the children of a parent, a record counts for the first one it has.
+    Children(String),
+}
+
+impl TagLevel {
+    pub fn name(&self) -> &str {
+        match self {
+            TagLevel::Tag => "All Tags",
+            TagLevel::Parent => "Parents",
+            TagLevel::Children(parent) => parent,
+        }
+    }
+
+    /// The series a record with `tags` counts for.
+    pub fn series<'a>(&self, tags: &'a [String]) -> Vec<&'a str> {
+        let mut series: Vec<&str> = Vec::new();
+        match self {
+            TagLevel::Tag | TagLevel::Parent => {
+                for tag in tags {
+                    let name = match self {
+                        TagLevel::Parent => tag_parent(tag),
+                        _ => tag.as_str(),
+                    };
+                    if !series.contains(&name) {
+                        series.push(name);
+                    }
+                }
+                if series.is_empty() {
+                    series.push(UNTAGGED);
+                }
+            }
+            TagLevel::Children(parent) => {
+                series.extend(
+                    tags.iter()
+                        .find(|t| tag_parent(t) == parent)
+                        .map(|t| t.as_str()),
+                );
+            }
+        }
+        series
+    }
+}
+
+/// Deserialized by `deserialize_tags`.
+#[derive(Serialize, Clone)]
+pub struct TagSetting {
+    /// Stays the same when the tag is renamed or reordered.
+    pub id: u64,
+    pub name: String,
+    pub color: [u8; 3],
+    /// Archived tags can't be selected for new records.
+    pub archived: bool,
+    pub description: String,
+}
+
+impl TagSetting {
+    /// The color is picked by the id, like the series of a plot.
+    pub fn new(id: u64, name: String) -> Self {
+        let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+        let index = id.saturating_sub(1) as f32;
+        let color: Color32 = Hsva::new((index * golden_ratio).fract(), 0.85, 0.5, 1.0).into();
+        Self {
+            id,
+            name,
+            color: [color.r(), color.g(), color.b()],
+            archived: false,
+            description: String::new(),
+        }
+    }
+
+    pub fn color(&self) -> Color32 {
+        let [r, g, b] = self.color;
+        Color32::from_rgb(r, g, b)
+    }
+
+    pub fn parent(&self) -> &str {
+        tag_parent(&self.name)
+    }
+
+    /// The name without the parent.
+    pub fn leaf(&self) -> &str {
+        self.name
+            .split_once(TAG_SEPARATOR)
+            .map_or(&self.name, |(_, child)| child)
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct TimerSetting {
     pub name: String,
     pub icon: String,
@@ -270,3 +754,122 @@ impl TimerSetting {
         }
     }
 }
+
+impl Default for TimerSetting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SequenceSetting {
+    pub name: String,
+    pub icon: String,
+    /// Timer names started one after another in every round.
+    pub timers: Vec<String>,
+    pub rounds: u32,
+    /// Timer that replaces the last step of the final round.
+    pub long_break: Option<String>,
+    /// Seconds to wait after a timeout before the next timer starts.
+    pub grace_secs: u64,
+}
+
+impl SequenceSetting {
+    pub fn new() -> Self {
+        Self {
+            name: "new".to_string(),
+            icon: String::new(),
+            timers: Vec::new(),
+            rounds: 1,
+            long_break: None,
+            grace_secs: 0,
+        }
+    }
+}
+
+impl Default for SequenceSetting {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Working time goals in minutes, 0 for no goal.
+#[derive(Deserialize, Serialize, Clone, Default)]
+pub struct GoalSetting {
+    #[serde(default)]
+    pub daily: u64,
+    #[serde(default)]
+    pub weekly: u64,
+    #[serde(default)]
+    pub tags: Vec<TagGoal>,
+}
+
+impl GoalSetting {
+    pub fn tag(&self, tag: &str) -> Option<&TagGoal> {
+        self.tags.iter().find(|g| g.tag == tag)
+    }
+
+    /// Replaces the goal of `tag`, a goal without any limit is removed.
+    pub fn set_tag(&mut self, goal: TagGoal) {
+        self.tags.retain(|g| g.tag != goal.tag);
+        if goal.daily > 0 || goal.weekly > 0 {
+            self.tags.push(goal);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TagGoal {
+    pub tag: String,
+    #[serde(default)]
+    pub daily: u64,
+    #[serde(default)]
+    pub weekly: u64,
+}
+
+impl TagGoal {
+    pub fn new() -> Self {
+        Self {
+            tag: String::new(),
+            daily: 0,
+            weekly: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+pub enum BudgetPeriod {
+    #[default]
+    Week,
+    Month,
+}
+
+impl BudgetPeriod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Week => "Week",
+            Self::Month => "Month",
+        }
+    }
+}
+
+/// Hours a tag may use in each week or month.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct TagBudget {
+    pub tag: String,
+    #[serde(default)]
+    pub hours: u64,
+    #[serde(default)]
+    pub period: BudgetPeriod,
+}
+
+impl TagBudget {
+    pub fn new() -> Self {
+        Self {
+            tag: String::new(),
+            hours: 0,
+            period: BudgetPeriod::Week,
+        }
+    }
+}
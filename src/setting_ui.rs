@@ -1,18 +1,46 @@
-use eframe::egui::{self, Grid, Id, Label, Modal, TextWrapMode, Ui, vec2};
+use chrono::{NaiveTime, Weekday};
+use eframe::egui::{
+    self, ComboBox, DragValue, Grid, Id, Label, Modal, RichText, Sides, TextWrapMode, Ui, vec2,
+};
 use rfd::FileDialog;
 
-use crate::{MyColor, setting::Setting};
+use crate::{
+    MyColor,
+    backup::{self, Backup, Scheduler},
+    error::Error,
+    error_ui::{self, Retry},
+    history::History,
+    setting::{self, Setting},
+};
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "Monday"),
+    (Weekday::Tue, "Tuesday"),
+    (Weekday::Wed, "Wednesday"),
+    (Weekday::Thu, "Thursday"),
+    (Weekday::Fri, "Friday"),
+    (Weekday::Sat, "Saturday"),
+    (Weekday::Sun, "Sunday"),
+];
 
 pub struct SettingWindow {
     show: bool,
+    day_start: String,
+    /// The backup picked to restore, or why it can't be.
+    restore: Option<Result<Backup, Error>>,
 }
 
 impl SettingWindow {
     pub fn new() -> Self {
-        Self { show: false }
+        Self {
+            show: false,
+            day_start: String::new(),
+            restore: None,
+        }
     }
 
-    pub fn show(&mut self) {
+    pub fn show(&mut self, setting: &Setting) {
+        self.day_start = setting.day_start().format("%H:%M").to_string();
         self.show = true;
     }
 
@@ -20,7 +48,15 @@ impl SettingWindow {
         self.show
     }
 
-    pub fn ui(&mut self, ui: &mut Ui, setting: &mut Setting) {
+    /// Returns true if a backup is restored.
+    pub fn ui(
+        &mut self,
+        ui: &mut Ui,
+        setting: &mut Setting,
+        history: &mut History,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        let mut restored = false;
         if self.show {
             let modal = Modal::new(Id::new("setting")).backdrop_color(MyColor::MODAL_BG);
             let response = modal.show(ui.ctx(), |ui| {
@@ -70,6 +106,125 @@ impl SettingWindow {
 
                     ui.end_row();
 
+                    ui.label("Day starts at:");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_sized(
+                                [60.0, 20.0],
+                                egui::TextEdit::singleline(&mut self.day_start),
+                            )
+                            .on_hover_text("Work before this time counts for the previous day.")
+                            .lost_focus()
+                        {
+                            match NaiveTime::parse_from_str(&self.day_start, "%H:%M") {
+                                Ok(time) => setting.set_day_start(time),
+                                Err(_) => {
+                                    self.day_start = setting.day_start().format("%H:%M").to_string()
+                                }
+                            }
+                        }
+                        ui.label("HH:MM");
+                    });
+                    ui.end_row();
+
+                    ui.label("Week starts on:");
+                    let week_start = setting.week_start();
+                    ComboBox::from_id_salt("week_start")
+                        .selected_text(
+                            WEEKDAYS
+                                .iter()
+                                .find(|(d, _)| *d == week_start)
+                                .map_or("", |(_, name)| *name),
+                        )
+                        .show_ui(ui, |ui| {
+                            for (day, name) in WEEKDAYS {
+                                if ui.selectable_label(day == week_start, name).clicked() {
+                                    setting.set_week_start(day);
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Goals:");
+                    ui.vertical(|ui| {
+                        let goal = setting.mut_goal();
+                        ui.horizontal(|ui| {
+                            ui.add(goal_drag_value(&mut goal.daily));
+                            ui.label("per day");
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(goal_drag_value(&mut goal.weekly));
+                            ui.label("per week");
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Backup:");
+                    ui.vertical(|ui| {
+                        let dir = setting.backup_dir();
+                        ui.add(
+                            Label::new(dir.display().to_string()).wrap_mode(TextWrapMode::Truncate),
+                        );
+                        ui.horizontal(|ui| {
+                            if ui.button("Set directory").clicked()
+                                && let Some(dir) =
+                                    FileDialog::new().set_directory(&dir).pick_folder()
+                            {
+                                setting.mut_backup().dir = dir.display().to_string();
+                            }
+                            if ui.button("Reset").clicked() {
+                                setting.mut_backup().dir.clear();
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            ui.label("Keep");
+                            ui.add(
+                                DragValue::new(&mut setting.mut_backup().keep)
+                                    .range(1..=100)
+                                    .suffix(" copies"),
+                            )
+                            .on_hover_text("It backs up on startup and then daily.");
+                        });
+                        if !scheduler.status.is_empty() {
+                            ui.add(
+                                Label::new(RichText::new(&scheduler.status).weak())
+                                    .wrap_mode(TextWrapMode::Truncate),
+                            )
+                            .on_hover_text(&scheduler.status);
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.button("Back Up Now").clicked() {
+                                scheduler.back_up(history, setting).ok();
+                            }
+                            if ui.button("Restore from Backup").clicked()
+                                && let Some(file) = FileDialog::new()
+                                    .add_filter("Backup", &[backup::EXTENSION])
+                                    .set_directory(&dir)
+                                    .pick_file()
+                            {
+                                self.restore = Some(Backup::read(&file));
+                            }
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Trash:");
+                    ui.horizontal(|ui| {
+                        let mut days = setting.trash_days();
+                        ui.label("Keep deleted records for");
+                        if ui
+                            .add(
+                                DragValue::new(&mut days)
+                                    .range(setting::TRASH_DAYS)
+                                    .suffix(" days"),
+                            )
+                            .changed()
+                        {
+                            setting.set_trash_days(days);
+                        }
+                    });
+                    ui.end_row();
+
                     const VERSION: &str = env!("CARGO_PKG_VERSION");
                     ui.label("Version:");
                     ui.label(VERSION);
@@ -87,9 +242,104 @@ impl SettingWindow {
             if response.should_close() {
                 let theme = ui.ctx().options(|opt| opt.theme_preference);
                 setting.set_theme(theme.into());
-                setting.save();
+                error_ui::check(
+                    ui.ctx(),
+                    setting.save(),
+                    "Saving the setting",
+                    Some(Retry::SaveSetting),
+                );
                 self.show = false;
             }
+            restored = self.restore_ui(ui, setting, history, scheduler);
         }
+        restored
     }
+
+    /// Returns true if the backup is restored.
+    fn restore_ui(
+        &mut self,
+        ui: &mut Ui,
+        setting: &mut Setting,
+        history: &mut History,
+        scheduler: &mut Scheduler,
+    ) -> bool {
+        let Some(restore) = self.restore.as_mut() else {
+            return false;
+        };
+
+        let mut close = false;
+        let mut restored = false;
+        let modal = Modal::new(Id::new("setting_restore")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            ui.set_width(400.0);
+            ui.heading("Restore from Backup");
+            ui.add_space(10.0);
+            match restore {
+                Ok(backup) => {
+                    ui.label(format!(
+                        "The backup from {} has {} records.",
+                        backup.created().format("%Y-%m-%d %H:%M:%S"),
+                        backup.record_count()
+                    ));
+                    ui.label(
+                        "The current history and setting will be replaced. \
+                         They are backed up first.",
+                    );
+                }
+                Err(e) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("The backup can't be restored: {e}"),
+                    );
+                }
+            }
+            ui.add_space(20.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if let Ok(backup) = restore
+                        && ui.button("Restore").clicked()
+                    {
+                        // Without a backup of the current data it can't be undone.
+                        let restoring = scheduler
+                            .back_up(history, setting)
+                            .map_err(|e| {
+                                Error::Invalid(format!("The current data can't be backed up: {e}"))
+                            })
+                            .and_then(|_| backup.restore(history, setting));
+                        match restoring {
+                            Ok(()) => {
+                                ui.ctx().set_theme(setting.theme());
+                                self.day_start = setting.day_start().format("%H:%M").to_string();
+                                restored = true;
+                            }
+                            Err(e) => *restore = Err(e),
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        close = true;
+                    }
+                },
+            );
+        });
+        if restored || close || response.should_close() {
+            self.restore = None;
+        }
+        restored
+    }
+}
+
+/// Minutes of a goal, 0 for no goal.
+pub fn goal_drag_value(minutes: &mut u64) -> DragValue<'_> {
+    DragValue::new(minutes)
+        .range(0..=10080)
+        .speed(5)
+        .custom_formatter(|v, _| {
+            if v == 0.0 {
+                "None".to_string()
+            } else {
+                format!("{} m", v)
+            }
+        })
 }
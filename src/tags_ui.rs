@@ -1,14 +1,36 @@
 use std::sync::Arc;
 
-use eframe::egui::{self, Button, Color32, Frame, Id, Modal, Sides, Ui, vec2};
+use eframe::egui::{
+    self, Button, CollapsingHeader, Color32, ComboBox, DragValue, Frame, Id, InnerResponse, Modal,
+    PopupCloseBehavior, RichText, Sides, Ui, vec2,
+};
 
-use crate::{MyColor, setting::Setting};
+use crate::{
+    MyColor,
+    error_ui::{self, Retry},
+    history::History,
+    setting::{self, BudgetPeriod, Setting, TagBudget, TagGoal, TagLevel, TagSetting},
+    setting_ui,
+};
 
 pub struct TagsWindow {
     show: bool,
     reorder: bool,
     modify_index: Option<usize>,
-    modify_tag: String,
+    modify_tag: TagSetting,
+    modify_goal: TagGoal,
+    modify_budget: TagBudget,
+    merge_into: Option<usize>,
+    retag: Option<Retag>,
+}
+
+/// Rewrites the records of a renamed or merged tag.
+struct Retag {
+    from: String,
+    to: String,
+    count: usize,
+    /// The index of the merged tag, it's deleted after merging.
+    merge: Option<usize>,
 }
 
 impl TagsWindow {
@@ -17,7 +39,11 @@ impl TagsWindow {
             show: false,
             reorder: false,
             modify_index: None,
-            modify_tag: String::new(),
+            modify_tag: TagSetting::new(0, String::new()),
+            modify_goal: TagGoal::new(),
+            modify_budget: TagBudget::new(),
+            merge_into: None,
+            retag: None,
         }
     }
 
@@ -26,7 +52,8 @@ impl TagsWindow {
         self.show = true;
     }
 
-    pub fn ui(&mut self, ui: &mut Ui, setting: &mut Setting) {
+    /// Returns true if records in the history are retagged.
+    pub fn ui(&mut self, ui: &mut Ui, setting: &mut Setting, history: &mut History) -> bool {
         if self.show {
             let mut from = None;
             let mut to = None;
@@ -36,7 +63,9 @@ impl TagsWindow {
                 ui.horizontal(|ui| {
                     ui.heading("Tags");
                     if ui.button("New").clicked() {
-                        self.modify_tag.clear();
+                        self.modify_tag = setting.new_tag(String::new());
+                        self.modify_goal = TagGoal::new();
+                        self.modify_budget = TagBudget::new();
                         self.modify_index = Some(usize::MAX);
                     }
                     if ui
@@ -51,11 +80,15 @@ impl TagsWindow {
                 let frame = Frame::default().inner_margin(4.0);
                 let (_, dropped_payload) = ui.dnd_drop_zone::<usize, ()>(frame, |ui| {
                     ui.set_min_size(vec2(180.0, 64.0));
-                    for (i, tag) in setting.tags().iter().enumerate() {
-                        if let (Some(a), Some(b)) = self.item_ui(ui, i, tag) {
-                            from = Some(a);
-                            to = Some(b);
+                    if self.reorder {
+                        for (i, tag) in setting.tags().iter().enumerate() {
+                            if let (Some(a), Some(b)) = self.item_ui(ui, i, tag, setting) {
+                                from = Some(a);
+                                to = Some(b);
+                            }
                         }
+                    } else {
+                        self.tree_ui(ui, setting);
                     }
                 });
 
@@ -76,23 +109,57 @@ impl TagsWindow {
             }
 
             if response.should_close() {
-                setting.save();
+                error_ui::check(
+                    ui.ctx(),
+                    setting.save(),
+                    "Saving the setting",
+                    Some(Retry::SaveSetting),
+                );
                 self.show = false;
             }
         }
 
-        self.modify_tag_ui(ui, setting);
+        self.modify_tag_ui(ui, setting, history);
+        self.retag_ui(ui, setting, history)
+    }
+
+    /// Children are listed under a collapsible parent.
+    fn tree_ui(&mut self, ui: &mut Ui, setting: &Setting) {
+        let tags = setting.tags();
+        for (parent, children) in setting::tag_groups(tags.iter()) {
+            let index = |tag: &TagSetting| tags.iter().position(|t| t.id == tag.id).unwrap();
+            if let [tag] = children.as_slice()
+                && tag.name == parent
+            {
+                self.item_ui(ui, index(tag), tag, setting);
+                continue;
+            }
+            CollapsingHeader::new(parent)
+                .id_salt(("tags_group", parent))
+                .default_open(true)
+                .show(ui, |ui| {
+                    for tag in children {
+                        self.item_ui(ui, index(tag), tag, setting);
+                    }
+                });
+        }
     }
 
-    fn item_ui(&mut self, ui: &mut Ui, i: usize, tag: &str) -> (Option<Arc<usize>>, Option<usize>) {
+    fn item_ui(
+        &mut self,
+        ui: &mut Ui,
+        i: usize,
+        tag: &TagSetting,
+        setting: &Setting,
+    ) -> (Option<Arc<usize>>, Option<usize>) {
         let mut from: Option<Arc<usize>> = None;
         let mut to: Option<usize> = None;
 
-        let item_id = Id::new(("tags", tag, i));
+        let item_id = Id::new(("tags", &tag.name, i));
         if self.reorder {
             let response = ui
                 .dnd_drag_source(item_id, i, |ui| {
-                    ui.label(format!("\u{2B0D} {tag}"));
+                    ui.label(format!("\u{2B0D} {}", tag.name));
                 })
                 .response;
             if let (Some(pointer), Some(hovered_payload)) = (
@@ -122,14 +189,36 @@ impl TagsWindow {
                     to = Some(insert_row_idx);
                 }
             }
-        } else if ui.add(Button::new(tag).frame(false)).clicked() {
-            self.modify_tag = tag.to_string();
-            self.modify_index = Some(i);
+        } else {
+            let mut text = RichText::new(format!("\u{25CF} {}", tag.leaf()));
+            if tag.archived {
+                text = text.weak().italics();
+            }
+            let response = ui.add(Button::new(text).frame(false));
+            let response = if tag.description.is_empty() {
+                response
+            } else {
+                response.on_hover_text(&tag.description)
+            };
+            if response.clicked() {
+                self.modify_tag = tag.clone();
+                self.modify_goal = setting
+                    .goal()
+                    .tag(&tag.name)
+                    .cloned()
+                    .unwrap_or_else(TagGoal::new);
+                self.modify_budget = setting
+                    .budget(&tag.name)
+                    .cloned()
+                    .unwrap_or_else(TagBudget::new);
+                self.merge_into = None;
+                self.modify_index = Some(i);
+            }
         }
         (from, to)
     }
 
-    fn modify_tag_ui(&mut self, ui: &mut Ui, setting: &mut Setting) {
+    fn modify_tag_ui(&mut self, ui: &mut Ui, setting: &mut Setting, history: &History) {
         if let Some(index) = self.modify_index {
             let modal = Modal::new(Id::new("tags_modify_tag")).backdrop_color(MyColor::MODAL_BG);
             let response = modal.show(ui.ctx(), |ui| {
@@ -143,22 +232,149 @@ impl TagsWindow {
 
                 ui.horizontal(|ui| {
                     ui.add_space(10.0);
-                    ui.text_edit_singleline(&mut self.modify_tag);
+                    ui.color_edit_button_srgb(&mut self.modify_tag.color);
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.modify_tag.name)
+                            .hint_text("Name or Parent/Child"),
+                    );
+                    ui.add_space(10.0);
+                });
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(10.0);
+                    ui.add(
+                        egui::TextEdit::multiline(&mut self.modify_tag.description)
+                            .hint_text("Description")
+                            .desired_rows(2),
+                    );
+                    ui.add_space(10.0);
+                });
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.add_space(10.0);
+                    ui.checkbox(&mut self.modify_tag.archived, "Archived")
+                        .on_hover_text("Archived tags can't be selected for new records.");
+                });
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
                     ui.add_space(10.0);
+                    ui.label("Goal:");
+                    ui.add(setting_ui::goal_drag_value(&mut self.modify_goal.daily));
+                    ui.label("per day");
+                    ui.add(setting_ui::goal_drag_value(&mut self.modify_goal.weekly));
+                    ui.label("per week");
                 });
                 ui.add_space(10.0);
 
+                ui.horizontal(|ui| {
+                    ui.add_space(10.0);
+                    ui.label("Budget:");
+                    ui.add(
+                        DragValue::new(&mut self.modify_budget.hours)
+                            .range(0..=744)
+                            .custom_formatter(|v, _| {
+                                if v == 0.0 {
+                                    "None".to_string()
+                                } else {
+                                    format!("{v} h")
+                                }
+                            }),
+                    );
+                    ui.label("per");
+                    ComboBox::from_id_salt("tags_budget_period")
+                        .selected_text(self.modify_budget.period.name())
+                        .show_ui(ui, |ui| {
+                            for period in [BudgetPeriod::Week, BudgetPeriod::Month] {
+                                ui.selectable_value(
+                                    &mut self.modify_budget.period,
+                                    period,
+                                    period.name(),
+                                );
+                            }
+                        });
+                });
+                ui.add_space(10.0);
+
+                if index != usize::MAX {
+                    ui.horizontal(|ui| {
+                        ui.add_space(10.0);
+                        ui.label("Merge into:");
+                        let tags = setting.tags();
+                        ComboBox::from_id_salt("tags_merge_into")
+                            .selected_text(self.merge_into.map_or("", |i| tags[i].name.as_str()))
+                            .show_ui(ui, |ui| {
+                                for (i, tag) in tags.iter().enumerate() {
+                                    if i != index {
+                                        ui.selectable_value(
+                                            &mut self.merge_into,
+                                            Some(i),
+                                            &tag.name,
+                                        );
+                                    }
+                                }
+                            });
+                        if ui
+                            .add_enabled(self.merge_into.is_some(), Button::new("Merge"))
+                            .on_hover_text("Retag the records and delete this tag.")
+                            .clicked()
+                            && let Some(into) = self.merge_into
+                        {
+                            let from = tags[index].name.clone();
+                            self.retag = Some(Retag {
+                                count: history.count_tag(&from),
+                                from,
+                                to: tags[into].name.clone(),
+                                merge: Some(index),
+                            });
+                            self.modify_index = None;
+                        }
+                    });
+                    ui.add_space(10.0);
+                }
+
+                let name = setting::normalize_tag(&self.modify_tag.name);
+                let duplicate = setting
+                    .tags()
+                    .iter()
+                    .enumerate()
+                    .any(|(i, t)| i != index && t.name == name);
                 Sides::new().show(
                     ui,
                     |_ui| {},
                     |ui| {
-                        if ui.button("Save").clicked() {
-                            if !self.modify_tag.is_empty() {
+                        if ui
+                            .add_enabled(!duplicate, Button::new("Save"))
+                            .on_disabled_hover_text("The tag exists, merge into it instead.")
+                            .clicked()
+                        {
+                            self.modify_tag.name = name;
+                            if !self.modify_tag.name.is_empty() {
                                 if index == usize::MAX {
                                     setting.mut_tags().push(self.modify_tag.clone());
                                 } else {
-                                    setting.mut_tags()[index] = self.modify_tag.clone();
+                                    let old = std::mem::replace(
+                                        &mut setting.mut_tags()[index],
+                                        self.modify_tag.clone(),
+                                    );
+                                    setting.mut_goal().tags.retain(|g| g.tag != old.name);
+                                    setting.remove_budget(&old.name);
+                                    let count = history.count_tag(&old.name);
+                                    if old.name != self.modify_tag.name && count > 0 {
+                                        self.retag = Some(Retag {
+                                            from: old.name,
+                                            to: self.modify_tag.name.clone(),
+                                            count,
+                                            merge: None,
+                                        });
+                                    }
                                 }
+                                self.modify_goal.tag = self.modify_tag.name.clone();
+                                setting.mut_goal().set_tag(self.modify_goal.clone());
+                                self.modify_budget.tag = self.modify_tag.name.clone();
+                                setting.set_budget(self.modify_budget.clone());
                             }
                             self.modify_index = None;
                         }
@@ -168,7 +384,7 @@ impl TagsWindow {
                         }
 
                         if index != usize::MAX && ui.button("Delete").clicked() {
-                            setting.mut_tags().remove(index);
+                            remove_tag(setting, index);
                             self.modify_index = None;
                         }
                     },
@@ -179,4 +395,161 @@ impl TagsWindow {
             }
         }
     }
+
+    fn retag_ui(&mut self, ui: &mut Ui, setting: &mut Setting, history: &mut History) -> bool {
+        let mut retagged = false;
+        if let Some(retag) = self.retag.as_ref() {
+            let mut close = false;
+            let modal = Modal::new(Id::new("tags_retag")).backdrop_color(MyColor::MODAL_BG);
+            let response = modal.show(ui.ctx(), |ui| {
+                ui.set_width(300.0);
+                if retag.merge.is_some() {
+                    ui.heading("Merge Tag");
+                    ui.add_space(10.0);
+                    ui.label(format!(
+                        "Merge \"{}\" into \"{}\"? {} records will be retagged and \"{}\" \
+                         will be deleted.",
+                        retag.from, retag.to, retag.count, retag.from
+                    ));
+                } else {
+                    ui.heading("Rename Tag");
+                    ui.add_space(10.0);
+                    ui.label(format!(
+                        "Also rename \"{}\" to \"{}\" in {} records of the history?",
+                        retag.from, retag.to, retag.count
+                    ));
+                }
+                ui.add_space(20.0);
+
+                Sides::new().show(
+                    ui,
+                    |_ui| {},
+                    |ui| {
+                        let text = if retag.merge.is_some() {
+                            "Merge"
+                        } else {
+                            "Rename records"
+                        };
+                        if ui.button(text).clicked() {
+                            let renamed = history.rename_tag(&retag.from, &retag.to);
+                            if error_ui::check(ui.ctx(), renamed, "Renaming the tag", None)
+                                .is_some()
+                            {
+                                if let Some(index) = retag.merge {
+                                    remove_tag(setting, index);
+                                }
+                                retagged = true;
+                            }
+                            error_ui::check(
+                                ui.ctx(),
+                                setting.save(),
+                                "Saving the setting",
+                                Some(Retry::SaveSetting),
+                            );
+                            close = true;
+                        }
+                        let text = if retag.merge.is_some() {
+                            "Cancel"
+                        } else {
+                            "Keep records"
+                        };
+                        if ui.button(text).clicked() {
+                            close = true;
+                        }
+                    },
+                );
+            });
+            if close || response.should_close() {
+                self.retag = None;
+            }
+        }
+        retagged
+    }
+}
+
+/// Deletes a tag with its goal and budget.
+fn remove_tag(setting: &mut Setting, index: usize) {
+    let tag = setting.mut_tags().remove(index);
+    setting.mut_goal().tags.retain(|g| g.tag != tag.name);
+    setting.remove_budget(&tag.name);
+}
+
+/// A check box per tag, grouped by parent. Archived tags are hidden unless
+/// selected, `empty_text` is shown if none is. The inner value is the tag
+/// toggled by the user.
+pub fn tags_select_ui<'a>(
+    ui: &mut Ui,
+    id_salt: &str,
+    width: f32,
+    empty_text: &str,
+    setting: &'a Setting,
+    selected: impl Fn(&TagSetting) -> bool,
+) -> InnerResponse<Option<&'a TagSetting>> {
+    let names: Vec<&str> = setting
+        .tags()
+        .iter()
+        .filter(|t| selected(t))
+        .map(|t| t.name.as_str())
+        .collect();
+    let text = match names.is_empty() {
+        true => empty_text.to_string(),
+        false => names.join(", "),
+    };
+
+    let mut toggled = None;
+    let mut checkbox = |ui: &mut Ui, tag: &'a TagSetting, name: &str| {
+        let mut checked = selected(tag);
+        if ui
+            .checkbox(&mut checked, RichText::new(name).color(tag.color()))
+            .changed()
+        {
+            toggled = Some(tag);
+        }
+    };
+    let response = ComboBox::from_id_salt(id_salt)
+        .width(width)
+        .selected_text(text)
+        .close_behavior(PopupCloseBehavior::CloseOnClickOutside)
+        .show_ui(ui, |ui| {
+            let tags = setting.tags().iter().filter(|t| !t.archived || selected(t));
+            for (parent, children) in setting::tag_groups(tags) {
+                if children.len() == 1 && children[0].name == parent {
+                    checkbox(ui, children[0], parent);
+                    continue;
+                }
+                CollapsingHeader::new(parent)
+                    .id_salt((id_salt, parent))
+                    .default_open(children.iter().any(|t| selected(t)))
+                    .show(ui, |ui| {
+                        for tag in children {
+                            checkbox(ui, tag, tag.leaf());
+                        }
+                    });
+            }
+        })
+        .response;
+    InnerResponse::new(toggled, response)
+}
+
+/// Returns true if the level is changed.
+pub fn tag_level_ui(ui: &mut Ui, id_salt: &str, level: &mut TagLevel, parents: &[String]) -> bool {
+    let mut new_level = level.clone();
+    ComboBox::from_id_salt(id_salt)
+        .selected_text(level.name())
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut new_level, TagLevel::Tag, TagLevel::Tag.name());
+            ui.selectable_value(&mut new_level, TagLevel::Parent, TagLevel::Parent.name());
+            for parent in parents.iter() {
+                ui.selectable_value(&mut new_level, TagLevel::Children(parent.clone()), parent);
+            }
+        })
+        .response
+        .on_hover_text(
+            "Group the tags by parent, or show the children of a parent.\n\
+             A record with several tags counts fully for each of them, so the bars \
+             of the tags and the parents aren't stacked.",
+        );
+    let changed = new_level != *level;
+    *level = new_level;
+    changed
 }
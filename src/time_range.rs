@@ -0,0 +1,162 @@
+use crate::calendar::Calendar;
+use chrono::{Datelike, Days, Months, NaiveDate};
+use eframe::egui::{ComboBox, Ui};
+use egui_extras::DatePickerButton;
+use std::time::SystemTime;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum RangePreset {
+    Today,
+    Days7,
+    Days30,
+    ThisWeek,
+    LastWeek,
+    ThisMonth,
+    LastMonth,
+    All,
+    Custom,
+}
+
+impl RangePreset {
+    const ALL: [RangePreset; 9] = [
+        Self::Today,
+        Self::Days7,
+        Self::Days30,
+        Self::ThisWeek,
+        Self::LastWeek,
+        Self::ThisMonth,
+        Self::LastMonth,
+        Self::All,
+        Self::Custom,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Today => "Today",
+            Self::Days7 => "7 Days",
+            Self::Days30 => "30 Days",
+            Self::ThisWeek => "This Week",
+            Self::LastWeek => "Last Week",
+            Self::ThisMonth => "This Month",
+            Self::LastMonth => "Last Month",
+            Self::All => "All",
+            Self::Custom => "Custom",
+        }
+    }
+}
+
+/// A range of whole days shared by the History and Chart windows.
+pub struct TimeRange {
+    preset: RangePreset,
+    from: NaiveDate,
+    to: NaiveDate,
+    calendar: Calendar,
+}
+
+impl TimeRange {
+    pub fn new(calendar: Calendar) -> Self {
+        let today = calendar.today();
+        let mut range = Self {
+            preset: RangePreset::Days7,
+            from: today,
+            to: today,
+            calendar,
+        };
+        range.set_preset(RangePreset::Days7);
+        range
+    }
+
+    fn set_preset(&mut self, preset: RangePreset) {
+        self.preset = preset;
+        let today = self.calendar.today();
+        let week_start = self.calendar.week_start(today);
+        let month_start = today.with_day(1).unwrap();
+        (self.from, self.to) = match preset {
+            RangePreset::Today => (today, today),
+            RangePreset::Days7 => (today - Days::new(6), today),
+            RangePreset::Days30 => (today - Days::new(29), today),
+            RangePreset::ThisWeek => (week_start, today),
+            RangePreset::LastWeek => (week_start - Days::new(7), week_start - Days::new(1)),
+            RangePreset::ThisMonth => (month_start, today),
+            RangePreset::LastMonth => (month_start - Months::new(1), month_start - Days::new(1)),
+            RangePreset::All | RangePreset::Custom => (self.from, self.to),
+        };
+    }
+
+    /// Recomputes presets relative to today, e.g. after midnight or after
+    /// the calendar setting is changed.
+    pub fn refresh(&mut self, calendar: Calendar) {
+        self.calendar = calendar;
+        self.set_preset(self.preset);
+    }
+
+    pub fn calendar(&self) -> &Calendar {
+        &self.calendar
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.preset == RangePreset::All
+    }
+
+    /// The first day, `None` for all the history.
+    pub fn from(&self) -> Option<NaiveDate> {
+        (!self.is_all()).then_some(self.from)
+    }
+
+    /// The last day, not later than today.
+    pub fn to(&self) -> NaiveDate {
+        if self.is_all() {
+            self.calendar.today()
+        } else {
+            self.to.min(self.calendar.today())
+        }
+    }
+
+    pub fn start(&self) -> SystemTime {
+        match self.from() {
+            Some(from) => self.calendar.start_of(from),
+            None => SystemTime::UNIX_EPOCH,
+        }
+    }
+
+    pub fn end(&self) -> SystemTime {
+        self.calendar.start_of(self.to() + Days::new(1))
+    }
+
+    /// Returns true if the range is changed.
+    pub fn ui(&mut self, ui: &mut Ui) -> bool {
+        let mut changed = false;
+        ComboBox::from_id_salt("time_range")
+            .selected_text(self.preset.name())
+            .show_ui(ui, |ui| {
+                for preset in RangePreset::ALL {
+                    if ui
+                        .selectable_label(self.preset == preset, preset.name())
+                        .clicked()
+                    {
+                        self.set_preset(preset);
+                        changed = true;
+                    }
+                }
+            });
+
+        if !self.is_all() {
+            let mut from = self.from;
+            let mut to = self.to;
+            let from_changed = ui
+                .add(DatePickerButton::new(&mut from).id_salt("time_range_from"))
+                .changed();
+            ui.label("~");
+            let to_changed = ui
+                .add(DatePickerButton::new(&mut to).id_salt("time_range_to"))
+                .changed();
+            if from_changed || to_changed {
+                self.preset = RangePreset::Custom;
+                self.from = from.min(to);
+                self.to = to.max(from);
+                changed = true;
+            }
+        }
+        changed
+    }
+}
@@ -1,17 +1,21 @@
 use crate::setting::TimerSetting;
-use std::time::{Instant, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum Status {
     Stopped,
     Started,
+    Paused,
     TimeOut,
 }
 
 pub struct Timer {
     count: u64,
+    /// Active time accumulated before the last start or resume.
+    active: Duration,
     start_instant: Instant,
     start_time: SystemTime,
+    pause_count: u32,
     status: Status,
     setting: Option<TimerSetting>,
 }
@@ -21,19 +25,62 @@ impl Timer {
         Self {
             status: Status::Stopped,
             count: 0,
+            active: Duration::ZERO,
             start_instant: Instant::now(),
             start_time: SystemTime::now(),
+            pause_count: 0,
             setting: None,
         }
     }
 
     pub fn start(&mut self, setting: &TimerSetting) {
         self.setting = Some(setting.clone());
+        self.count = 0;
+        self.active = Duration::ZERO;
         self.start_instant = Instant::now();
         self.start_time = SystemTime::now();
+        self.pause_count = 0;
         self.status = Status::Started;
     }
 
+    /// Restores a session saved before a crash, the timer is left paused.
+    pub fn restore(
+        &mut self,
+        setting: &TimerSetting,
+        start_time: SystemTime,
+        active_secs: u64,
+        pause_count: u32,
+    ) {
+        self.start(setting);
+        self.start_time = start_time;
+        self.active = Duration::from_secs(active_secs);
+        self.count = active_secs;
+        self.pause_count = pause_count;
+        self.status = Status::Paused;
+    }
+
+    pub fn pause(&mut self) {
+        if matches!(self.status, Status::Started | Status::TimeOut) {
+            self.active += self.start_instant.elapsed();
+            self.count = self.active.as_secs();
+            self.pause_count += 1;
+            self.status = Status::Paused;
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if self.status == Status::Paused
+            && let Some(s) = self.setting.as_ref()
+        {
+            self.start_instant = Instant::now();
+            self.status = if self.count >= s.limit_time * 60 {
+                Status::TimeOut
+            } else {
+                Status::Started
+            };
+        }
+    }
+
     pub fn stop(&mut self) -> Option<(u64, String)> {
         self.status = Status::Stopped;
         if let Some(s) = self.setting.take()
@@ -52,6 +99,10 @@ impl Timer {
         }
     }
 
+    pub fn for_work(&self) -> bool {
+        self.setting.as_ref().is_some_and(|s| s.for_work)
+    }
+
     pub fn notify(&self) -> bool {
         if let Some(s) = self.setting.as_ref() {
             s.notify
@@ -68,12 +119,28 @@ impl Timer {
         &self.start_time
     }
 
+    pub fn active_secs(&self) -> u64 {
+        self.count
+    }
+
+    pub fn pause_count(&self) -> u32 {
+        self.pause_count
+    }
+
+    /// Seconds of active time left until the limit, `None` once it is reached.
+    pub fn remaining_secs(&self) -> Option<u64> {
+        let limit_count = self.setting.as_ref()?.limit_time * 60;
+        limit_count.checked_sub(self.count).filter(|secs| *secs > 0)
+    }
+
     pub fn update(&mut self) -> (bool, String) {
         let mut is_timeout = false;
         let counter_string = if let Some(setting) = self.setting.as_ref() {
             let limit_count = setting.limit_time * 60;
-            self.count = self.start_instant.elapsed().as_secs();
-            if self.status != Status::TimeOut && self.count >= limit_count {
+            if self.status != Status::Paused {
+                self.count = (self.active + self.start_instant.elapsed()).as_secs();
+            }
+            if self.status == Status::Started && self.count >= limit_count {
                 self.status = Status::TimeOut;
                 is_timeout = true;
             }
@@ -4,6 +4,7 @@ use eframe::egui::{self, Button, Color32, Frame, Id, Modal, Sides, Ui, vec2};
 
 use crate::{
     MyColor,
+    error_ui::{self, Retry},
     setting::{Setting, TimerSetting},
 };
 
@@ -11,6 +12,8 @@ pub struct TimersWindow {
     show: bool,
     reorder: bool,
     delete_index: Option<usize>,
+    /// The old and the new name of a timer renamed in this frame.
+    renamed: Option<(String, String)>,
     timer_info_list: Vec<TimerInfo>,
 }
 
@@ -20,6 +23,7 @@ impl TimersWindow {
             show: false,
             reorder: false,
             delete_index: None,
+            renamed: None,
             timer_info_list: Vec::new(),
         }
     }
@@ -87,8 +91,17 @@ impl TimersWindow {
                 self.refresh_info(setting);
             }
 
+            if let Some((old, new)) = self.renamed.take() {
+                setting.rename_timer(&old, &new);
+            }
+
             if response.should_close() {
-                setting.save();
+                error_ui::check(
+                    ui.ctx(),
+                    setting.save(),
+                    "Saving the setting",
+                    Some(Retry::SaveSetting),
+                );
                 self.show = false;
             }
         }
@@ -161,10 +174,12 @@ impl TimersWindow {
                             .add_sized([240.0, 20.0], egui::TextEdit::singleline(&mut timer.name))
                             .lost_focus()
                         {
-                            if timer_info.name.is_empty() {
-                                timer_info.name = timer.name.clone();
-                            } else {
+                            if timer.name.is_empty() {
                                 timer.name = timer_info.name.clone();
+                            } else if timer.name != timer_info.name {
+                                let old =
+                                    std::mem::replace(&mut timer_info.name, timer.name.clone());
+                                self.renamed = Some((old, timer.name.clone()));
                             }
                         }
                         if ui.button("Delete").clicked() {
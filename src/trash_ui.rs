@@ -0,0 +1,196 @@
+use std::time::SystemTime;
+
+use chrono::{DateTime, Local};
+use eframe::egui::{Button, Id, Label, Modal, RichText, Sides, Ui};
+use egui_extras::{Column, TableBuilder};
+
+use crate::{
+    MyColor, error_ui,
+    history::{History, Record, RecordId},
+};
+
+pub struct TrashWindow {
+    show: bool,
+    /// Deletion time and record, the latest deleted first.
+    records: Vec<(SystemTime, Record)>,
+    empty_confirm: bool,
+}
+
+impl TrashWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            records: Vec::new(),
+            empty_confirm: false,
+        }
+    }
+
+    pub fn show(&mut self, history: &History) {
+        self.records = history.trash_records();
+        self.show = true;
+    }
+
+    fn close(&mut self) {
+        self.show = false;
+        self.records = Vec::new();
+    }
+
+    /// Returns true if records are restored.
+    pub fn ui(&mut self, ui: &mut Ui, history: &mut History, trash_days: u64) -> bool {
+        if !self.show {
+            return false;
+        }
+
+        let mut restore = Vec::new();
+        let mut purge = Vec::new();
+        let modal = Modal::new(Id::new("trash")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            if let Some(r) = crate::get_viewport_inner_rect(ui.ctx()) {
+                ui.set_max_height(r.height() - 45.0);
+            }
+
+            ui.horizontal(|ui| {
+                ui.heading("Trash");
+                ui.add_space(20.0);
+                if ui
+                    .add_enabled(!self.records.is_empty(), Button::new("Restore All"))
+                    .clicked()
+                {
+                    restore = self.records.iter().map(|(_, r)| r.id).collect();
+                }
+                if ui
+                    .add_enabled(!self.records.is_empty(), Button::new("Empty Trash"))
+                    .clicked()
+                {
+                    self.empty_confirm = true;
+                }
+                ui.add_space(ui.available_width() - 30.0);
+                if ui.button("\u{274C}").clicked() {
+                    self.close();
+                }
+            });
+            ui.label(format!(
+                "Deleted records are kept for {trash_days} days, then deleted permanently."
+            ));
+            ui.separator();
+
+            if self.records.is_empty() {
+                ui.label("The trash is empty.");
+                return;
+            }
+            TableBuilder::new(ui)
+                .striped(true)
+                .column(Column::remainder().at_least(140.0))
+                .column(Column::remainder().at_least(140.0))
+                .column(Column::remainder().at_least(80.0))
+                .column(Column::remainder().at_least(60.0))
+                .column(Column::remainder().at_least(100.0).clip(true))
+                .column(Column::auto())
+                .header(20.0, |mut header| {
+                    for text in ["Deleted", "Start Time", "Duration", "Tags", "Note", ""] {
+                        header.col(|ui| {
+                            ui.strong(text);
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(18.0, self.records.len(), |mut row| {
+                        let (deleted, record) = &self.records[row.index()];
+                        row.col(|ui| {
+                            let time: DateTime<Local> = (*deleted).into();
+                            ui.label(
+                                RichText::new(time.format("%Y-%m-%d %H:%M").to_string())
+                                    .monospace(),
+                            );
+                        });
+                        row.col(|ui| {
+                            let time: DateTime<Local> = record.start_time.into();
+                            ui.label(
+                                RichText::new(time.format("%Y-%m-%d %H:%M:%S").to_string())
+                                    .monospace(),
+                            );
+                        });
+                        row.col(|ui| {
+                            let text = crate::timer::secs_to_string(record.duration, "");
+                            ui.label(RichText::new(text).monospace());
+                        });
+                        row.col(|ui| {
+                            ui.label(record.tags.join(", "));
+                        });
+                        row.col(|ui| {
+                            ui.add(Label::new(&record.note).truncate())
+                                .on_hover_text(&record.note);
+                        });
+                        row.col(|ui| {
+                            if ui.button("\u{21BA}").on_hover_text("Restore").clicked() {
+                                restore.push(record.id);
+                            }
+                            if ui
+                                .button("\u{274E}")
+                                .on_hover_text("Delete permanently")
+                                .clicked()
+                            {
+                                purge.push(record.id);
+                            }
+                        });
+                    });
+                });
+        });
+        if response.should_close() {
+            self.close();
+        }
+        self.empty_ui(ui, &mut purge);
+
+        if !purge.is_empty()
+            && error_ui::check(
+                ui.ctx(),
+                history.purge_records(&purge),
+                "Deleting from the trash",
+                None,
+            )
+            .is_some()
+        {
+            self.records.retain(|(_, r)| !purge.contains(&r.id));
+        }
+        if restore.is_empty() {
+            return false;
+        }
+        let restored = history.restore_records(&restore);
+        if error_ui::check(ui.ctx(), restored, "Restoring records", None).is_none() {
+            return false;
+        }
+        self.records.retain(|(_, r)| !restore.contains(&r.id));
+        true
+    }
+
+    fn empty_ui(&mut self, ui: &mut Ui, purge: &mut Vec<RecordId>) {
+        if !self.empty_confirm {
+            return;
+        }
+        let modal = Modal::new(Id::new("trash_empty")).backdrop_color(MyColor::MODAL_BG);
+        let response = modal.show(ui.ctx(), |ui| {
+            ui.set_width(350.0);
+            ui.heading(format!(
+                "Are you sure you want to delete {} records permanently?",
+                self.records.len()
+            ));
+            ui.add_space(32.0);
+            Sides::new().show(
+                ui,
+                |_ui| {},
+                |ui| {
+                    if ui.button("Yes").clicked() {
+                        purge.extend(self.records.iter().map(|(_, r)| r.id));
+                        self.empty_confirm = false;
+                    }
+                    if ui.button("No").clicked() {
+                        self.empty_confirm = false;
+                    }
+                },
+            );
+        });
+        if response.should_close() {
+            self.empty_confirm = false;
+        }
+    }
+}